@@ -0,0 +1,38 @@
+use sha2::{Digest, Sha256};
+use crate::enums::ANONYMOUS;
+use crate::round::Game;
+
+/// Replaces player names with salted hashes. The same salt gives the same pseudonym for a name
+/// in every game, so per-player statistics still work on the anonymized corpus.
+pub struct Anonymizer {
+    salt: String,
+    drop_profile: bool,
+}
+
+impl Anonymizer {
+    /// With `drop_profile`, `sex` and `rate` are cleared too, as they can identify players together with the ranks.
+    pub fn new(salt: &str, drop_profile: bool) -> Self {
+        Self { salt: salt.to_string(), drop_profile }
+    }
+
+    /// First 16 hex digits of SHA-256 over the salt and the name.
+    pub fn pseudonym(&self, name: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+        hasher.finalize()[..8].iter().map(|x| format!("{:02x}", x)).collect()
+    }
+
+    /// `NoName` is kept as it doesn't identify anyone. Names are hashed as they are,
+    /// so games parsed with and without `raw_names` get different pseudonyms.
+    pub fn apply(&self, game: &mut Game) {
+        for name in game.id.iter_mut().filter(|x| x.as_str() != ANONYMOUS) {
+            *name = self.pseudonym(name);
+        }
+        if self.drop_profile {
+            game.sex = Default::default();
+            game.rate = Default::default();
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::enums::{MajEvent, NakiKind, Pai, ReachStep};
+use crate::river::{River, Rivers};
+use crate::round::{Round, RoundData};
+
+/// A meld on the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Meld {
+    pub kind: NakiKind,
+    /// The tiles from the hand followed by the called tile. A kakan adds its tile last.
+    pub tiles: Vec<Pai>,
+    /// The tile taken from another player.
+    pub called: Option<Pai>,
+    pub target: Option<u8>,
+}
+
+/// The table during a round, rebuilt by applying the events in order.
+#[derive(Debug, Clone)]
+pub struct Board {
+    /// Concealed tiles sorted by tile id, without the tile just drawn.
+    pub hands: [Vec<Pai>; 4],
+    /// The tile drawn by a player who has not discarded yet.
+    pub drawn: [Option<Pai>; 4],
+    pub melds: [Vec<Meld>; 4],
+    pub dora_markers: Vec<Pai>,
+    pub scores: [i32; 4],
+    pub kyotaku: u8,
+    pub riichi: [bool; 4],
+    rivers: Rivers,
+}
+
+fn take(hand: &mut Vec<Pai>, pai: Pai, actor: u8) -> Result<(), String> {
+    match hand.iter().position(|&x| x == pai) {
+        Some(i) => {
+            hand.remove(i);
+            Ok(())
+        }
+        None => Err(format!("{} is not in the hand of player {}", pai, actor)),
+    }
+}
+
+impl Board {
+    pub fn new(data: &RoundData) -> Self {
+        let mut hands = data.tehais.each_ref().map(|x| x.to_vec());
+        for hand in hands.iter_mut() {
+            hand.sort_by_key(|&x| u8::from(x));
+        }
+        Self {
+            hands,
+            drawn: [None; 4],
+            melds: Default::default(),
+            dora_markers: vec![data.dora_marker],
+            scores: data.scores,
+            kyotaku: data.kyotaku,
+            riichi: [false; 4],
+            rivers: Rivers::default(),
+        }
+    }
+
+    pub fn rivers(&self) -> &[River; 4] {
+        &self.rivers.rivers
+    }
+
+    /// Concealed tiles including the tile just drawn.
+    pub fn concealed(&self, actor: u8) -> usize {
+        self.hands[actor as usize].len() + self.drawn[actor as usize].is_some() as usize
+    }
+
+    /// Puts the drawn tile into the sorted hand.
+    fn merge_drawn(&mut self, actor: usize) {
+        if let Some(pai) = self.drawn[actor].take() {
+            let hand = &mut self.hands[actor];
+            let i = hand.partition_point(|&x| u8::from(x) < u8::from(pai));
+            hand.insert(i, pai);
+        }
+    }
+
+    /// Applies one event. Tiles that should come from a hand but are not in it are reported
+    /// as an error, and the rest of the event is still applied.
+    pub fn apply(&mut self, event: &MajEvent) -> Result<(), String> {
+        self.rivers.apply(event);
+        let mut result = Ok(());
+        match event {
+            MajEvent::Tsumo { actor, pai, .. } => {
+                self.merge_drawn(*actor as usize);
+                self.drawn[*actor as usize] = Some(*pai);
+            }
+            MajEvent::Dahai { actor, pai, .. } => {
+                let a = *actor as usize;
+                if self.drawn[a] == Some(*pai) {
+                    self.drawn[a] = None;
+                } else {
+                    result = take(&mut self.hands[a], *pai, *actor);
+                    self.merge_drawn(a);
+                }
+            }
+            MajEvent::Naki { actor, consumed, pai, target, kind, .. } => {
+                let a = *actor as usize;
+                self.merge_drawn(a);
+                let from_hand = if *kind == NakiKind::Kakan { pai.iter().copied().collect() } else { consumed.clone() };
+                result = from_hand.iter().try_for_each(|&x| take(&mut self.hands[a], x, *actor));
+                if *kind == NakiKind::Kakan {
+                    let pon = self.melds[a].iter_mut().find(|x| x.kind == NakiKind::Pon && consumed.iter().all(|y| x.tiles.contains(y)));
+                    match pon {
+                        Some(pon) => {
+                            pon.kind = NakiKind::Kakan;
+                            pon.tiles.extend(pai.iter());
+                        }
+                        None => result = result.and(Err(format!("no pon of player {} to add {} to", actor, consumed[0]))),
+                    }
+                } else {
+                    let mut tiles = consumed.clone();
+                    tiles.extend(pai.iter());
+                    self.melds[a].push(Meld { kind: *kind, tiles, called: *pai, target: *target });
+                }
+            }
+            MajEvent::Dora { dora_marker } => self.dora_markers.push(*dora_marker),
+            MajEvent::Reach { actor, step: ReachStep::Riichi, .. } => self.riichi[*actor as usize] = true,
+            MajEvent::Reach { actor, step: ReachStep::RiichiAccepted, scores, .. } => {
+                self.scores[*actor as usize] -= 1000;
+                self.scores = scores.unwrap_or(self.scores);
+                self.kyotaku += 1;
+            }
+            MajEvent::Agari { deltas, .. } => {
+                (0..4).for_each(|i| self.scores[i] += deltas[i]);
+                self.kyotaku = 0;
+            }
+            MajEvent::Ryuukyoku { deltas, .. } => (0..4).for_each(|i| self.scores[i] += deltas[i]),
+            _ => {}
+        }
+        result
+    }
+}
+
+impl Round {
+    /// The table before the event at `end`.
+    pub fn board_at(&self, end: usize) -> Board {
+        let mut board = Board::new(&self.data);
+        for event in &self.game[..end] {
+            let _ = board.apply(event);
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Board;
+    use crate::enums::{NakiKind, Pai};
+    use crate::testing::RoundBuilder;
+
+    const TEHAIS: [&str; 4] = ["123456789m1234p", "123456789p1177z", "111123456789s1m", "2223334445556z"];
+
+    fn sorted(mut tiles: Vec<Pai>) -> Vec<Pai> {
+        tiles.sort_by_key(|&x| u8::from(x));
+        tiles
+    }
+
+    #[test]
+    fn kakan_is_added_to_the_pon() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let called = b.tsumo(0, "7z");
+        b.dahai(0, called);
+        let pon = b.dealt(1, "77z");
+        b.naki(1, NakiKind::Pon, &pon, Some(called), Some(0));
+        b.dahai(1, b.dealt(1, "1p")[0]);
+        let added = b.tsumo(1, "7z");
+        b.naki(1, NakiKind::Kakan, &[pon[0], pon[1], called], Some(added), Some(0));
+        let rinshan = b.tsumo(1, "9s");
+        let round = b.build();
+
+        let board = round.board_at(round.game.len());
+        assert_eq!(board.melds[1].len(), 1);
+        let meld = &board.melds[1][0];
+        assert_eq!(meld.kind, NakiKind::Kakan);
+        assert_eq!(meld.tiles, [pon[0], pon[1], called, added]);
+        assert_eq!((meld.called, meld.target), (Some(called), Some(0)));
+        assert_eq!(board.hands[1].len(), 10);
+        assert!(!board.hands[1].contains(&added));
+        assert_eq!(board.drawn[1], Some(rinshan));
+    }
+
+    #[test]
+    fn ankan_leaves_the_rest_of_the_hand_and_the_rinshan_tile() {
+        let mut b = RoundBuilder::new(2, TEHAIS);
+        let first = b.tsumo(2, "9m");
+        let kan = b.dealt(2, "1111s");
+        b.naki(2, NakiKind::Ankan, &kan, None, None);
+        let rinshan = b.tsumo(2, "7p");
+        let dora = b.dora("3m");
+        let round = b.build();
+
+        let board = round.board_at(round.game.len());
+        let mut rest = round.data.tehais[2].iter().copied().filter(|x| !kan.contains(x)).collect::<Vec<_>>();
+        rest.push(first);
+        assert_eq!(board.hands[2], sorted(rest));
+        assert_eq!(board.drawn[2], Some(rinshan));
+        assert_eq!(board.concealed(2), 11);
+        assert_eq!(board.melds[2].len(), 1);
+        assert_eq!((board.melds[2][0].kind, board.melds[2][0].called), (NakiKind::Ankan, None));
+        assert_eq!(board.dora_markers, [round.data.dora_marker, dora]);
+
+        // The kan tiles were already gone before the rinshan draw.
+        assert_eq!(round.board_at(2).concealed(2), 10);
+    }
+
+    #[test]
+    fn missing_tile_is_reported() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let elsewhere = b.dealt(1, "1p")[0];
+        b.tsumo(0, "7z");
+        b.dahai(0, elsewhere);
+        let round = b.build();
+        let mut board = Board::new(&round.data);
+        assert!(board.apply(&round.game[0]).is_ok());
+        assert!(board.apply(&round.game[1]).is_err());
+    }
+}
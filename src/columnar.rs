@@ -0,0 +1,500 @@
+use std::fs::{create_dir_all, read_dir, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use arrow_array::builder::{BooleanBuilder, Float32Builder, Int32Builder, ListBuilder, StringBuilder, UInt16Builder, UInt32Builder, UInt8Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use crate::enums::{MajEvent, Pai};
+use crate::round::Game;
+
+/// Games buffered before a new part file is written for every table.
+const GAMES_PER_PART: usize = 10000;
+
+fn append_pais<'a>(builder: &mut ListBuilder<StringBuilder>, pais: impl IntoIterator<Item = &'a Pai>) {
+    for pai in pais {
+        builder.values().append_value(pai.to_string());
+    }
+    builder.append(true);
+}
+
+fn batch(columns: Vec<(&str, ArrayRef, bool)>) -> RecordBatch {
+    RecordBatch::try_from_iter_with_nullable(columns).unwrap()
+}
+
+#[derive(Default)]
+struct Games {
+    log_id: StringBuilder,
+    date: StringBuilder,
+    hour: UInt8Builder,
+    room: StringBuilder,
+    r#type: UInt16Builder,
+    lobby: UInt16Builder,
+    rounds: UInt32Builder,
+}
+
+impl Games {
+    fn finish(&mut self) -> RecordBatch {
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("date", Arc::new(self.date.finish()), true),
+            ("hour", Arc::new(self.hour.finish()), true),
+            ("room", Arc::new(self.room.finish()), true),
+            ("type", Arc::new(self.r#type.finish()), false),
+            ("lobby", Arc::new(self.lobby.finish()), true),
+            ("rounds", Arc::new(self.rounds.finish()), false),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Players {
+    log_id: StringBuilder,
+    seat: UInt8Builder,
+    name: StringBuilder,
+    dan: UInt8Builder,
+    dan_name: StringBuilder,
+    rate: Float32Builder,
+    sex: StringBuilder,
+    owari: Int32Builder,
+}
+
+impl Players {
+    fn finish(&mut self) -> RecordBatch {
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("seat", Arc::new(self.seat.finish()), false),
+            ("name", Arc::new(self.name.finish()), false),
+            ("dan", Arc::new(self.dan.finish()), false),
+            ("dan_name", Arc::new(self.dan_name.finish()), false),
+            ("rate", Arc::new(self.rate.finish()), false),
+            ("sex", Arc::new(self.sex.finish()), false),
+            ("owari", Arc::new(self.owari.finish()), false),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Rounds {
+    log_id: StringBuilder,
+    round: UInt32Builder,
+    bakaze: StringBuilder,
+    kyoku: UInt8Builder,
+    honba: UInt8Builder,
+    kyotaku: UInt8Builder,
+    oya: UInt8Builder,
+    dora_marker: StringBuilder,
+    scores: ListBuilder<Int32Builder>,
+    tehais: [ListBuilder<StringBuilder>; 4],
+    outcome: StringBuilder,
+    winners: ListBuilder<UInt8Builder>,
+    houjuu: UInt8Builder,
+    renchan: BooleanBuilder,
+}
+
+impl Rounds {
+    fn finish(&mut self) -> RecordBatch {
+        let [tehai0, tehai1, tehai2, tehai3] = &mut self.tehais;
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("round", Arc::new(self.round.finish()), false),
+            ("bakaze", Arc::new(self.bakaze.finish()), false),
+            ("kyoku", Arc::new(self.kyoku.finish()), false),
+            ("honba", Arc::new(self.honba.finish()), false),
+            ("kyotaku", Arc::new(self.kyotaku.finish()), false),
+            ("oya", Arc::new(self.oya.finish()), false),
+            ("dora_marker", Arc::new(self.dora_marker.finish()), false),
+            ("scores", Arc::new(self.scores.finish()), false),
+            ("tehai0", Arc::new(tehai0.finish()), false),
+            ("tehai1", Arc::new(tehai1.finish()), false),
+            ("tehai2", Arc::new(tehai2.finish()), false),
+            ("tehai3", Arc::new(tehai3.finish()), false),
+            ("outcome", Arc::new(self.outcome.finish()), true),
+            ("winners", Arc::new(self.winners.finish()), true),
+            ("houjuu", Arc::new(self.houjuu.finish()), true),
+            ("renchan", Arc::new(self.renchan.finish()), true),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Events {
+    log_id: StringBuilder,
+    round: UInt32Builder,
+    seq: UInt32Builder,
+    r#type: StringBuilder,
+    actor: UInt8Builder,
+    junme: UInt8Builder,
+    pai: StringBuilder,
+    tsumogiri: BooleanBuilder,
+    target: UInt8Builder,
+}
+
+impl Events {
+    fn finish(&mut self) -> RecordBatch {
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("round", Arc::new(self.round.finish()), false),
+            ("seq", Arc::new(self.seq.finish()), false),
+            ("type", Arc::new(self.r#type.finish()), false),
+            ("actor", Arc::new(self.actor.finish()), true),
+            ("junme", Arc::new(self.junme.finish()), true),
+            ("pai", Arc::new(self.pai.finish()), true),
+            ("tsumogiri", Arc::new(self.tsumogiri.finish()), true),
+            ("target", Arc::new(self.target.finish()), true),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Melds {
+    log_id: StringBuilder,
+    round: UInt32Builder,
+    seq: UInt32Builder,
+    actor: UInt8Builder,
+    junme: UInt8Builder,
+    kind: StringBuilder,
+    pai: StringBuilder,
+    consumed: ListBuilder<StringBuilder>,
+    target: UInt8Builder,
+}
+
+impl Melds {
+    fn finish(&mut self) -> RecordBatch {
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("round", Arc::new(self.round.finish()), false),
+            ("seq", Arc::new(self.seq.finish()), false),
+            ("actor", Arc::new(self.actor.finish()), false),
+            ("junme", Arc::new(self.junme.finish()), true),
+            ("kind", Arc::new(self.kind.finish()), false),
+            ("pai", Arc::new(self.pai.finish()), true),
+            ("consumed", Arc::new(self.consumed.finish()), false),
+            ("target", Arc::new(self.target.finish()), true),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Agaris {
+    log_id: StringBuilder,
+    round: UInt32Builder,
+    seq: UInt32Builder,
+    actor: UInt8Builder,
+    fromwho: UInt8Builder,
+    hora_order: UInt8Builder,
+    paowho: UInt8Builder,
+    honba: UInt8Builder,
+    kyotaku: UInt8Builder,
+    junme: UInt8Builder,
+    han: UInt8Builder,
+    hu: UInt8Builder,
+    score: Int32Builder,
+    yaku: ListBuilder<UInt8Builder>,
+    machi: StringBuilder,
+    hai: ListBuilder<StringBuilder>,
+    dora_marker: ListBuilder<StringBuilder>,
+    ura_marker: ListBuilder<StringBuilder>,
+    deltas: ListBuilder<Int32Builder>,
+}
+
+impl Agaris {
+    fn finish(&mut self) -> RecordBatch {
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("round", Arc::new(self.round.finish()), false),
+            ("seq", Arc::new(self.seq.finish()), false),
+            ("actor", Arc::new(self.actor.finish()), false),
+            ("fromwho", Arc::new(self.fromwho.finish()), false),
+            ("hora_order", Arc::new(self.hora_order.finish()), false),
+            ("paowho", Arc::new(self.paowho.finish()), true),
+            ("honba", Arc::new(self.honba.finish()), false),
+            ("kyotaku", Arc::new(self.kyotaku.finish()), false),
+            ("junme", Arc::new(self.junme.finish()), false),
+            ("han", Arc::new(self.han.finish()), false),
+            ("hu", Arc::new(self.hu.finish()), false),
+            ("score", Arc::new(self.score.finish()), false),
+            ("yaku", Arc::new(self.yaku.finish()), false),
+            ("machi", Arc::new(self.machi.finish()), false),
+            ("hai", Arc::new(self.hai.finish()), false),
+            ("dora_marker", Arc::new(self.dora_marker.finish()), false),
+            ("ura_marker", Arc::new(self.ura_marker.finish()), true),
+            ("deltas", Arc::new(self.deltas.finish()), false),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Ryuukyokus {
+    log_id: StringBuilder,
+    round: UInt32Builder,
+    seq: UInt32Builder,
+    reason: StringBuilder,
+    tenpai: ListBuilder<BooleanBuilder>,
+    nagashi: ListBuilder<BooleanBuilder>,
+    deltas: ListBuilder<Int32Builder>,
+}
+
+impl Ryuukyokus {
+    fn finish(&mut self) -> RecordBatch {
+        batch(vec![
+            ("log_id", Arc::new(self.log_id.finish()), false),
+            ("round", Arc::new(self.round.finish()), false),
+            ("seq", Arc::new(self.seq.finish()), false),
+            ("reason", Arc::new(self.reason.finish()), false),
+            ("tenpai", Arc::new(self.tenpai.finish()), false),
+            ("nagashi", Arc::new(self.nagashi.finish()), false),
+            ("deltas", Arc::new(self.deltas.finish()), false),
+        ])
+    }
+}
+
+/// Flattened Arrow tables of a batch of games, keyed by log id, round index and event index.
+#[derive(Default)]
+pub struct ArrowTables {
+    len: usize,
+    games: Games,
+    players: Players,
+    rounds: Rounds,
+    events: Events,
+    melds: Melds,
+    agari: Agaris,
+    ryuukyoku: Ryuukyokus,
+}
+
+impl ArrowTables {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, log_id: &str, game: &Game) {
+        self.len += 1;
+        self.games.log_id.append_value(log_id);
+        self.games.date.append_option(game.meta.as_ref().map(|x| &x.date));
+        self.games.hour.append_option(game.meta.as_ref().map(|x| x.hour));
+        self.games.room.append_option(game.meta.as_ref().map(|x| &x.room));
+        self.games.r#type.append_value(game.r#type);
+        self.games.lobby.append_option(game.lobby);
+        self.games.rounds.append_value(game.games.len() as u32);
+
+        for seat in 0..4 {
+            self.players.log_id.append_value(log_id);
+            self.players.seat.append_value(seat as u8);
+            self.players.name.append_value(&game.id[seat]);
+            self.players.dan.append_value(game.dan[seat].into());
+            self.players.dan_name.append_value(game.dan[seat].name());
+            self.players.rate.append_value(game.rate[seat]);
+            self.players.sex.append_value(&game.sex[seat]);
+            self.players.owari.append_value(game.owari[seat]);
+        }
+
+        for (round_idx, round) in game.games.iter().enumerate() {
+            let round_idx = round_idx as u32;
+            let data = &round.data;
+            let rounds = &mut self.rounds;
+            rounds.log_id.append_value(log_id);
+            rounds.round.append_value(round_idx);
+            rounds.bakaze.append_value(&data.bakaze);
+            rounds.kyoku.append_value(data.kyoku);
+            rounds.honba.append_value(data.honba);
+            rounds.kyotaku.append_value(data.kyotaku);
+            rounds.oya.append_value(data.oya);
+            rounds.dora_marker.append_value(data.dora_marker.to_string());
+            rounds.scores.append_value(data.scores.map(Some));
+            for (builder, tehai) in rounds.tehais.iter_mut().zip(data.tehais.iter()) {
+                append_pais(builder, tehai);
+            }
+            let outcome = round.outcome();
+            rounds.outcome.append_option(outcome.as_ref().map(|x| x.kind.name()));
+            rounds.winners.append_option(outcome.as_ref().map(|x| x.winners.iter().map(|&x| Some(x))));
+            rounds.houjuu.append_option(outcome.as_ref().and_then(|x| x.houjuu));
+            rounds.renchan.append_option(outcome.as_ref().map(|x| x.renchan));
+
+            for ((seq, event), hora_order) in round.game.iter().enumerate().zip(round.hora_orders()) {
+                self.push_event(log_id, round_idx, seq as u32, event, hora_order);
+            }
+        }
+    }
+
+    fn push_event(&mut self, log_id: &str, round: u32, seq: u32, event: &MajEvent, hora_order: Option<u8>) {
+        let events = &mut self.events;
+        events.log_id.append_value(log_id);
+        events.round.append_value(round);
+        events.seq.append_value(seq);
+        events.r#type.append_value(event.type_name());
+        events.actor.append_option(event.actor());
+        events.junme.append_option(event.junme());
+        events.pai.append_option(event.pai().map(|x| x.to_string()));
+        events.tsumogiri.append_option(event.tsumogiri());
+        events.target.append_option(event.target());
+
+        match event {
+            MajEvent::Naki { junme, actor, consumed, pai, target, kind } => {
+                let melds = &mut self.melds;
+                melds.log_id.append_value(log_id);
+                melds.round.append_value(round);
+                melds.seq.append_value(seq);
+                melds.actor.append_value(*actor);
+                melds.junme.append_option(*junme);
+                melds.kind.append_value(kind.name());
+                melds.pai.append_option(pai.map(|x| x.to_string()));
+                append_pais(&mut melds.consumed, consumed);
+                melds.target.append_option(*target);
+            }
+            MajEvent::Agari { honba, kyotaku, junme, hai, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas, .. } => {
+                let agari = &mut self.agari;
+                agari.log_id.append_value(log_id);
+                agari.round.append_value(round);
+                agari.seq.append_value(seq);
+                agari.actor.append_value(*actor);
+                agari.fromwho.append_value(*fromwho);
+                agari.hora_order.append_value(hora_order.unwrap());
+                agari.paowho.append_option(*paowho);
+                agari.honba.append_value(*honba);
+                agari.kyotaku.append_value(*kyotaku);
+                agari.junme.append_value(*junme);
+                agari.han.append_value(*han);
+                agari.hu.append_value(*hu);
+                agari.score.append_value(*score);
+                agari.yaku.append_value(yaku.iter().map(|&x| Some(x)));
+                agari.machi.append_value(machi.to_string());
+                append_pais(&mut agari.hai, hai);
+                append_pais(&mut agari.dora_marker, dora_marker);
+                match ura_marker {
+                    Some(ura_marker) => append_pais(&mut agari.ura_marker, ura_marker),
+                    None => agari.ura_marker.append_null(),
+                }
+                agari.deltas.append_value(deltas.map(Some));
+            }
+            MajEvent::Ryuukyoku { reason, tenpai, deltas, nagashi, .. } => {
+                let ryuukyoku = &mut self.ryuukyoku;
+                ryuukyoku.log_id.append_value(log_id);
+                ryuukyoku.round.append_value(round);
+                ryuukyoku.seq.append_value(seq);
+                ryuukyoku.reason.append_value(reason.to_string());
+                ryuukyoku.tenpai.append_value(tenpai.map(Some));
+                ryuukyoku.nagashi.append_value(nagashi.map(Some));
+                ryuukyoku.deltas.append_value(deltas.map(Some));
+            }
+            _ => {}
+        }
+    }
+
+    /// Takes the buffered rows out as one record batch per table.
+    pub fn finish(&mut self) -> Vec<(&'static str, RecordBatch)> {
+        self.len = 0;
+        vec![
+            ("games", self.games.finish()),
+            ("players", self.players.finish()),
+            ("rounds", self.rounds.finish()),
+            ("events", self.events.finish()),
+            ("melds", self.melds.finish()),
+            ("agari", self.agari.finish()),
+            ("ryuukyoku", self.ryuukyoku.finish()),
+        ]
+    }
+}
+
+/// Panics if the newest part of a table was written with other columns than this version writes,
+/// since readers of the dataset expect every part of a table to have the same schema.
+fn check_schemas(dir: &Path, part: usize) {
+    for (name, batch) in ArrowTables::default().finish() {
+        let path = dir.join(name).join(format!("part-{:05}.parquet", part));
+        let Ok(file) = File::open(&path) else { continue };
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        if builder.schema().fields() != batch.schema().fields() {
+            panic!("{} has other columns than this version writes, write the dataset to a new directory", path.display());
+        }
+    }
+}
+
+/// A directory with one subdirectory of Parquet part files per table.
+/// Part numbers continue after the existing ones, so repeated runs append to the dataset,
+/// as long as the existing parts have the same columns.
+pub struct ParquetDataset {
+    dir: PathBuf,
+    part: usize,
+    tables: ArrowTables,
+}
+
+impl ParquetDataset {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let part = read_dir(dir.join("games")).map(|entries| {
+            entries.filter_map(|entry| {
+                let name = entry.unwrap().file_name().into_string().unwrap();
+                name.strip_prefix("part-")?.strip_suffix(".parquet")?.parse::<usize>().ok()
+            }).map(|x| x + 1).max().unwrap_or(0)
+        }).unwrap_or(0);
+        if part > 0 {
+            check_schemas(&dir, part - 1);
+        }
+        Self { dir, part, tables: ArrowTables::default() }
+    }
+
+    pub fn append(&mut self, log_id: &str, game: &Game) {
+        self.tables.push(log_id, game);
+        if self.tables.len() >= GAMES_PER_PART {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.tables.is_empty() {
+            return;
+        }
+        for (name, batch) in self.tables.finish() {
+            let dir = self.dir.join(name);
+            create_dir_all(&dir).unwrap();
+            let file = File::create(dir.join(format!("part-{:05}.parquet", self.part))).unwrap();
+            let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+            writer.write(&batch).unwrap();
+            writer.close().unwrap();
+        }
+        self.part += 1;
+    }
+
+    pub fn finish(mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::sync::Arc;
+    use arrow_array::builder::StringBuilder;
+    use arrow_array::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use super::ParquetDataset;
+    use crate::testing::{fixture, temp_dir};
+
+    #[test]
+    fn runs_append_parts() {
+        let dir = temp_dir("parquet-append");
+        for log_id in ["a", "b"] {
+            let mut dataset = ParquetDataset::open(&dir);
+            dataset.append(log_id, &fixture());
+            dataset.finish();
+        }
+        assert!(dir.join("rounds/part-00001.parquet").exists());
+        assert_eq!(ParquetDataset::open(&dir).part, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "other columns")]
+    fn parts_with_other_columns_are_refused() {
+        let dir = temp_dir("parquet-schema");
+        std::fs::create_dir_all(dir.join("games")).unwrap();
+        let batch = RecordBatch::try_from_iter([("log_id", Arc::new(StringBuilder::new().finish()) as _)]).unwrap();
+        let file = File::create(dir.join("games/part-00000.parquet")).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        ParquetDataset::open(&dir);
+    }
+}
@@ -0,0 +1,345 @@
+use std::fs::{create_dir_all, File};
+use std::path::Path;
+use csv::{Reader, StringRecord, Writer, WriterBuilder};
+use serde::Serialize;
+use crate::enums::{MajEvent, Pai, ReachStep};
+use crate::round::Game;
+use crate::yaku::yaku_name;
+
+fn join_pai<'a>(pais: impl IntoIterator<Item = &'a Pai>) -> String {
+    pais.into_iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+#[derive(Default, Serialize)]
+struct GameRow<'a> {
+    log_id: &'a str,
+    date: Option<&'a str>,
+    hour: Option<u8>,
+    room: Option<&'a str>,
+    r#type: u16,
+    lobby: Option<u16>,
+    rounds: usize,
+    name0: &'a str,
+    name1: &'a str,
+    name2: &'a str,
+    name3: &'a str,
+    dan0: u8,
+    dan1: u8,
+    dan2: u8,
+    dan3: u8,
+    rate0: f32,
+    rate1: f32,
+    rate2: f32,
+    rate3: f32,
+    owari0: i32,
+    owari1: i32,
+    owari2: i32,
+    owari3: i32,
+}
+
+#[derive(Default, Serialize)]
+struct RoundRow<'a> {
+    log_id: &'a str,
+    round: usize,
+    bakaze: &'a str,
+    kyoku: u8,
+    honba: u8,
+    kyotaku: u8,
+    oya: u8,
+    dora_marker: String,
+    score0: i32,
+    score1: i32,
+    score2: i32,
+    score3: i32,
+    outcome: Option<&'static str>,
+    winners: String,
+    houjuu: Option<u8>,
+    renchan: Option<bool>,
+}
+
+#[derive(Default, Serialize)]
+struct DiscardRow<'a> {
+    log_id: &'a str,
+    round: usize,
+    seq: usize,
+    actor: u8,
+    junme: u8,
+    pai: String,
+    tsumogiri: bool,
+    riichi: bool,
+}
+
+#[derive(Default, Serialize)]
+struct CallRow<'a> {
+    log_id: &'a str,
+    round: usize,
+    seq: usize,
+    actor: u8,
+    junme: Option<u8>,
+    kind: &'static str,
+    pai: Option<String>,
+    consumed: String,
+    target: Option<u8>,
+}
+
+#[derive(Default, Serialize)]
+struct WinRow<'a> {
+    log_id: &'a str,
+    round: usize,
+    seq: usize,
+    actor: u8,
+    fromwho: u8,
+    /// Position in the head bump order of a multiple ron, 0 for the winner who takes the sticks.
+    hora_order: u8,
+    paowho: Option<u8>,
+    honba: u8,
+    kyotaku: u8,
+    junme: u8,
+    han: u8,
+    hu: u8,
+    score: i32,
+    yaku: String,
+    machi: String,
+    hai: String,
+    dora_marker: String,
+    ura_marker: Option<String>,
+    delta0: i32,
+    delta1: i32,
+    delta2: i32,
+    delta3: i32,
+}
+
+#[derive(Default, Serialize)]
+struct DrawRow<'a> {
+    log_id: &'a str,
+    round: usize,
+    seq: usize,
+    reason: String,
+    tenpai0: bool,
+    tenpai1: bool,
+    tenpai2: bool,
+    tenpai3: bool,
+    nagashi0: bool,
+    nagashi1: bool,
+    nagashi2: bool,
+    nagashi3: bool,
+    delta0: i32,
+    delta1: i32,
+    delta2: i32,
+    delta3: i32,
+}
+
+/// Column names of a row type, taken from a default row so they follow the fields.
+fn header<T: Serialize + Default>() -> StringRecord {
+    let mut writer = Writer::from_writer(Vec::new());
+    writer.serialize(T::default()).unwrap();
+    let data = writer.into_inner().unwrap();
+    Reader::from_reader(data.as_slice()).headers().unwrap().clone()
+}
+
+/// Writes the header when the file is created, so a table without rows still has its columns.
+fn table<T: Serialize + Default>(dir: &Path, name: &str) -> Writer<File> {
+    let mut writer = WriterBuilder::new().has_headers(false).from_path(dir.join(name).with_extension("csv")).unwrap();
+    writer.write_record(&header::<T>()).unwrap();
+    writer
+}
+
+/// One CSV file per entity in a directory: `games`, `rounds`, `discards`, `calls`, `wins` and `draws`.
+/// Tiles are written in the JSON notation and separated by spaces inside a cell.
+pub struct CsvTables {
+    games: Writer<File>,
+    rounds: Writer<File>,
+    discards: Writer<File>,
+    calls: Writer<File>,
+    wins: Writer<File>,
+    draws: Writer<File>,
+}
+
+impl CsvTables {
+    pub fn create<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref();
+        create_dir_all(dir).unwrap();
+        Self {
+            games: table::<GameRow>(dir, "games"),
+            rounds: table::<RoundRow>(dir, "rounds"),
+            discards: table::<DiscardRow>(dir, "discards"),
+            calls: table::<CallRow>(dir, "calls"),
+            wins: table::<WinRow>(dir, "wins"),
+            draws: table::<DrawRow>(dir, "draws"),
+        }
+    }
+
+    pub fn write(&mut self, log_id: &str, game: &Game) {
+        let meta = game.meta.as_ref();
+        self.games.serialize(GameRow {
+            log_id,
+            date: meta.map(|x| x.date.as_str()),
+            hour: meta.map(|x| x.hour),
+            room: meta.map(|x| x.room.as_str()),
+            r#type: game.r#type,
+            lobby: game.lobby,
+            rounds: game.games.len(),
+            name0: &game.id[0],
+            name1: &game.id[1],
+            name2: &game.id[2],
+            name3: &game.id[3],
+            dan0: game.dan[0].into(),
+            dan1: game.dan[1].into(),
+            dan2: game.dan[2].into(),
+            dan3: game.dan[3].into(),
+            rate0: game.rate[0],
+            rate1: game.rate[1],
+            rate2: game.rate[2],
+            rate3: game.rate[3],
+            owari0: game.owari[0],
+            owari1: game.owari[1],
+            owari2: game.owari[2],
+            owari3: game.owari[3],
+        }).unwrap();
+
+        for (round, r) in game.games.iter().enumerate() {
+            let data = &r.data;
+            let outcome = r.outcome();
+            self.rounds.serialize(RoundRow {
+                log_id,
+                round,
+                bakaze: &data.bakaze,
+                kyoku: data.kyoku,
+                honba: data.honba,
+                kyotaku: data.kyotaku,
+                oya: data.oya,
+                dora_marker: data.dora_marker.to_string(),
+                score0: data.scores[0],
+                score1: data.scores[1],
+                score2: data.scores[2],
+                score3: data.scores[3],
+                outcome: outcome.as_ref().map(|x| x.kind.name()),
+                winners: outcome.as_ref().map(|x| x.winners.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ")).unwrap_or_default(),
+                houjuu: outcome.as_ref().and_then(|x| x.houjuu),
+                renchan: outcome.as_ref().map(|x| x.renchan),
+            }).unwrap();
+
+            let mut declared = [false; 4];
+            let hora_orders = r.hora_orders();
+            for (seq, event) in r.game.iter().enumerate() {
+                match event {
+                    MajEvent::Reach { actor, step: ReachStep::Riichi, .. } => {
+                        declared[*actor as usize] = true;
+                    }
+                    MajEvent::Dahai { junme, actor, pai, tsumogiri } => {
+                        self.discards.serialize(DiscardRow {
+                            log_id,
+                            round,
+                            seq,
+                            actor: *actor,
+                            junme: *junme,
+                            pai: pai.to_string(),
+                            tsumogiri: *tsumogiri,
+                            riichi: std::mem::take(&mut declared[*actor as usize]),
+                        }).unwrap();
+                    }
+                    MajEvent::Naki { junme, actor, consumed, pai, target, kind } => {
+                        self.calls.serialize(CallRow {
+                            log_id,
+                            round,
+                            seq,
+                            actor: *actor,
+                            junme: *junme,
+                            kind: kind.name(),
+                            pai: pai.map(|x| x.to_string()),
+                            consumed: join_pai(consumed),
+                            target: *target,
+                        }).unwrap();
+                    }
+                    MajEvent::Agari { honba, kyotaku, junme, hai, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas, .. } => {
+                        self.wins.serialize(WinRow {
+                            log_id,
+                            round,
+                            seq,
+                            actor: *actor,
+                            fromwho: *fromwho,
+                            hora_order: hora_orders[seq].unwrap(),
+                            paowho: *paowho,
+                            honba: *honba,
+                            kyotaku: *kyotaku,
+                            junme: *junme,
+                            han: *han,
+                            hu: *hu,
+                            score: *score,
+                            yaku: yaku.iter().map(|&x| yaku_name(x)).collect::<Vec<&str>>().join(" "),
+                            machi: machi.to_string(),
+                            hai: join_pai(hai),
+                            dora_marker: join_pai(dora_marker),
+                            ura_marker: ura_marker.as_ref().map(join_pai),
+                            delta0: deltas[0],
+                            delta1: deltas[1],
+                            delta2: deltas[2],
+                            delta3: deltas[3],
+                        }).unwrap();
+                    }
+                    MajEvent::Ryuukyoku { reason, tenpai, deltas, nagashi, .. } => {
+                        self.draws.serialize(DrawRow {
+                            log_id,
+                            round,
+                            seq,
+                            reason: reason.to_string(),
+                            tenpai0: tenpai[0],
+                            tenpai1: tenpai[1],
+                            tenpai2: tenpai[2],
+                            tenpai3: tenpai[3],
+                            nagashi0: nagashi[0],
+                            nagashi1: nagashi[1],
+                            nagashi2: nagashi[2],
+                            nagashi3: nagashi[3],
+                            delta0: deltas[0],
+                            delta1: deltas[1],
+                            delta2: deltas[2],
+                            delta3: deltas[3],
+                        }).unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    pub fn finish(mut self) {
+        for writer in [&mut self.games, &mut self.rounds, &mut self.discards, &mut self.calls, &mut self.wins, &mut self.draws] {
+            writer.flush().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+    use super::CsvTables;
+    use crate::testing::{fixture, temp_dir};
+
+    const TABLES: [&str; 6] = ["games", "rounds", "discards", "calls", "wins", "draws"];
+
+    #[test]
+    fn empty_tables_have_headers() {
+        let dir = temp_dir("csv-empty");
+        CsvTables::create(&dir).finish();
+        for name in TABLES {
+            let text = read_to_string(dir.join(name).with_extension("csv")).unwrap();
+            assert!(text.starts_with("log_id,"), "{}", name);
+            assert_eq!(text.lines().count(), 1, "{}", name);
+        }
+    }
+
+    #[test]
+    fn headers_are_written_once() {
+        let dir = temp_dir("csv-rows");
+        let mut tables = CsvTables::create(&dir);
+        tables.write("a", &fixture());
+        tables.write("b", &fixture());
+        tables.finish();
+        for name in TABLES {
+            let text = read_to_string(dir.join(name).with_extension("csv")).unwrap();
+            assert_eq!(text.lines().filter(|x| x.starts_with("log_id,")).count(), 1, "{}", name);
+            assert!(text.lines().count() > 2, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use crate::round::Game;
+
+/// Serialization formats of `Game`. All of them keep the field names and the `type` tags,
+/// so every format can be read back into `Game`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    PrettyJson,
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl Encoding {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Encoding::PrettyJson | Encoding::Json => "json",
+            Encoding::MessagePack => "msgpack",
+            Encoding::Cbor => "cbor",
+        }
+    }
+}
+
+impl Game {
+    pub fn write_to<W: Write>(&self, mut writer: W, encoding: Encoding) {
+        match encoding {
+            Encoding::PrettyJson => serde_json::to_writer_pretty(&mut writer, self).unwrap(),
+            Encoding::Json => serde_json::to_writer(&mut writer, self).unwrap(),
+            Encoding::MessagePack => rmp_serde::encode::write_named(&mut writer, self).unwrap(),
+            Encoding::Cbor => ciborium::into_writer(self, &mut writer).unwrap(),
+        }
+        writer.flush().unwrap();
+    }
+
+    pub fn read_from<R: Read>(reader: R, encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::PrettyJson | Encoding::Json => serde_json::from_reader(reader).unwrap(),
+            Encoding::MessagePack => rmp_serde::from_read(reader).unwrap(),
+            Encoding::Cbor => ciborium::from_reader(reader).unwrap(),
+        }
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P, encoding: Encoding) {
+        self.write_to(BufWriter::new(File::create(path).unwrap()), encoding);
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P, encoding: Encoding) -> Self {
+        Self::read_from(BufReader::new(File::open(path).unwrap()), encoding)
+    }
+}
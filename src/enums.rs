@@ -1,4 +1,6 @@
-use serde::{Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize)]
 pub enum PaiColor {
@@ -33,6 +35,221 @@ impl From<u8> for Pai {
     }
 }
 
+/// Tile notation used in the JSON output, `0m` `0p` `0s` being the red fives.
+impl Display for Pai {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let num = if self.num == 5 && self.idx == 0 && self.color != PaiColor::Jihai { 0 } else { self.num };
+        let color = match self.color {
+            PaiColor::Manzu => 'm',
+            PaiColor::Pinzu => 'p',
+            PaiColor::Souzu => 's',
+            PaiColor::Jihai => 'z',
+            _ => panic!("Invalid color for Pai"),
+        };
+        write!(f, "{}{}", num, color)
+    }
+}
+
+impl From<Pai> for u8 {
+    fn from(value: Pai) -> Self {
+        let base = match value.color {
+            PaiColor::Manzu => 0,
+            PaiColor::Pinzu => 9,
+            PaiColor::Souzu => 18,
+            PaiColor::Jihai => 27,
+            _ => panic!("Invalid color for Pai"),
+        };
+        (base + value.num - 1) * 4 + value.idx
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NakiKind {
+    Chii,
+    Pon,
+    Kakan,
+    Ankan,
+    Daiminkan,
+    /// kita in sanma
+    Nukidora,
+}
+
+impl NakiKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NakiKind::Chii => "chii",
+            NakiKind::Pon => "pon",
+            NakiKind::Kakan => "kakan",
+            NakiKind::Ankan => "ankan",
+            NakiKind::Daiminkan => "daiminkan",
+            NakiKind::Nukidora => "nukidora",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReachStep {
+    /// step 1, the declaration
+    #[serde(rename = "riichi")]
+    Riichi,
+    /// step 2, the deposit after the declaration tile passed
+    #[serde(rename = "riichi_accepted")]
+    RiichiAccepted,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RyuukyokuReason {
+    /// exhaustive draw, RYUUKYOKU without a type attribute
+    #[default]
+    #[serde(rename = "howanpai")]
+    Howanpai,
+    /// kyuushu kyuuhai
+    #[serde(rename = "yao9")]
+    Yao9,
+    /// four riichi
+    #[serde(rename = "reach4")]
+    Reach4,
+    /// triple ron
+    #[serde(rename = "ron3")]
+    Ron3,
+    /// four kans by different players
+    #[serde(rename = "kan4")]
+    Kan4,
+    /// four identical winds discarded
+    #[serde(rename = "kaze4")]
+    Kaze4,
+    /// nagashi mangan
+    #[serde(rename = "nm")]
+    Nm,
+}
+
+impl FromStr for RyuukyokuReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "howanpai" => Ok(Self::Howanpai),
+            "yao9" => Ok(Self::Yao9),
+            "reach4" => Ok(Self::Reach4),
+            "ron3" => Ok(Self::Ron3),
+            "kan4" => Ok(Self::Kan4),
+            "kaze4" => Ok(Self::Kaze4),
+            "nm" => Ok(Self::Nm),
+            _ => Err(format!("Invalid ryuukyoku reason: {}", s)),
+        }
+    }
+}
+
+impl Display for RyuukyokuReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Self::Howanpai => "howanpai",
+            Self::Yao9 => "yao9",
+            Self::Reach4 => "reach4",
+            Self::Ron3 => "ron3",
+            Self::Kan4 => "kan4",
+            Self::Kaze4 => "kaze4",
+            Self::Nm => "nm",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// Name of players who are not logged in.
+pub const ANONYMOUS: &str = "NoName";
+
+/// Tenhou rank, in the order of the indices of the `dan` attribute of UN.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dan {
+    #[default]
+    Shinjin,
+    Kyu9,
+    Kyu8,
+    Kyu7,
+    Kyu6,
+    Kyu5,
+    Kyu4,
+    Kyu3,
+    Kyu2,
+    Kyu1,
+    Dan1,
+    Dan2,
+    Dan3,
+    Dan4,
+    Dan5,
+    Dan6,
+    Dan7,
+    Dan8,
+    Dan9,
+    Dan10,
+    Tenhou,
+}
+
+const DANS: [(Dan, &str, &str); 21] = [
+    (Dan::Shinjin, "新人", "Novice"),
+    (Dan::Kyu9, "9級", "9 kyu"),
+    (Dan::Kyu8, "8級", "8 kyu"),
+    (Dan::Kyu7, "7級", "7 kyu"),
+    (Dan::Kyu6, "6級", "6 kyu"),
+    (Dan::Kyu5, "5級", "5 kyu"),
+    (Dan::Kyu4, "4級", "4 kyu"),
+    (Dan::Kyu3, "3級", "3 kyu"),
+    (Dan::Kyu2, "2級", "2 kyu"),
+    (Dan::Kyu1, "1級", "1 kyu"),
+    (Dan::Dan1, "初段", "1 dan"),
+    (Dan::Dan2, "二段", "2 dan"),
+    (Dan::Dan3, "三段", "3 dan"),
+    (Dan::Dan4, "四段", "4 dan"),
+    (Dan::Dan5, "五段", "5 dan"),
+    (Dan::Dan6, "六段", "6 dan"),
+    (Dan::Dan7, "七段", "7 dan"),
+    (Dan::Dan8, "八段", "8 dan"),
+    (Dan::Dan9, "九段", "9 dan"),
+    (Dan::Dan10, "十段", "10 dan"),
+    (Dan::Tenhou, "天鳳", "Tenhou"),
+];
+
+impl Dan {
+    /// Japanese name as shown by the Tenhou client.
+    pub fn name(&self) -> &'static str {
+        DANS[*self as usize].1
+    }
+
+    pub fn english_name(&self) -> &'static str {
+        DANS[*self as usize].2
+    }
+}
+
+impl From<u8> for Dan {
+    fn from(value: u8) -> Self {
+        DANS.get(value as usize).expect("Invalid value for Dan").0
+    }
+}
+
+impl From<Dan> for u8 {
+    fn from(value: Dan) -> Self {
+        value as u8
+    }
+}
+
+/// Accepts both the Japanese and the English names.
+impl FromStr for Dan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DANS.iter().find(|(_, name, english_name)| s == *name || s.eq_ignore_ascii_case(english_name))
+            .map(|(dan, _, _)| *dan)
+            .ok_or_else(|| format!("Invalid dan: {}", s))
+    }
+}
+
+impl Display for Dan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 #[derive(Debug, Default)]
 pub enum MajEvent {
     #[default]
@@ -50,44 +267,48 @@ pub enum MajEvent {
         tehais: [[Pai; 13]; 4],
     },
     Ryuukyoku {
-        reason: String,
-        ///ryuukyoku
-        r#type: &'static str,
+        reason: RyuukyokuReason,
+        /// hands shown at the draw, tenpai hands on howanpai or the declarer's hand on yao9
+        tehais: [Option<Vec<Pai>>; 4],
+        tenpai: [bool; 4],
+        /// score changes from the sc attribute
+        deltas: [i32; 4],
+        /// nagashi mangan winners
+        nagashi: [bool; 4],
     },
     Dora {
         dora_marker: Pai,
-        /// dora
-        r#type: &'static str,
     },
     Reach {
         junme: u8,
         actor: u8,
-        /// riichi or riichi_accepted
-        r#type: &'static str,
+        step: ReachStep,
+        /// declaration tile, the discard following step 1
+        pai: Option<Pai>,
+        tsumogiri: Option<bool>,
+        /// scores after the deposit, from the ten attribute of step 2
+        scores: Option<[i32; 4]>,
     },
     Dahai {
         junme: u8,
         actor: u8,
         pai: Pai,
-        /// dahai
-        r#type: &'static str,
         tsumogiri: bool,
     },
     Tsumo {
         junme: u8,
         actor: u8,
         pai: Pai,
-        /// tsumo
-        r#type: &'static str,
     },
     Naki {
         junme: Option<u8>,
         actor: u8,
+        /// tiles from the hand, for a kakan the pon with its called tile last,
+        /// for an ankan the four tiles with the one named by `m` last
         consumed: Vec<Pai>,
         pai: Option<Pai>,
         target: Option<u8>,
-        /// chii pon kakan ankan daiminkan
-        r#type: &'static str,
+        kind: NakiKind,
     },
     Agari {
         honba: u8,
@@ -105,8 +326,82 @@ pub enum MajEvent {
         actor: u8,
         fromwho: u8,
         paowho: Option<u8>,
-        /// agari
-        r#type: &'static str,
+        /// score changes from the sc attribute
+        deltas: [i32; 4],
+    },
+    /// BYE, the client lost connection and tsumogiri is played automatically
+    Disconnect {
+        actor: u8,
+    },
+    /// UN with a single name, the client connected again
+    Reconnect {
+        actor: u8,
     },
 }
 
+impl MajEvent {
+    /// The `type` field of the JSON output.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            MajEvent::Unknown => "Unknown",
+            MajEvent::Init { .. } => "init",
+            MajEvent::Ryuukyoku { .. } => "ryuukyoku",
+            MajEvent::Dora { .. } => "dora",
+            MajEvent::Reach { step: ReachStep::Riichi, .. } => "riichi",
+            MajEvent::Reach { step: ReachStep::RiichiAccepted, .. } => "riichi_accepted",
+            MajEvent::Dahai { .. } => "dahai",
+            MajEvent::Tsumo { .. } => "tsumo",
+            MajEvent::Naki { kind, .. } => kind.name(),
+            MajEvent::Agari { .. } => "agari",
+            MajEvent::Disconnect { .. } => "disconnect",
+            MajEvent::Reconnect { .. } => "reconnect",
+        }
+    }
+
+    pub fn actor(&self) -> Option<u8> {
+        match self {
+            MajEvent::Reach { actor, .. } | MajEvent::Dahai { actor, .. } | MajEvent::Tsumo { actor, .. }
+            | MajEvent::Naki { actor, .. } | MajEvent::Agari { actor, .. }
+            | MajEvent::Disconnect { actor } | MajEvent::Reconnect { actor } => Some(*actor),
+            _ => None,
+        }
+    }
+
+    pub fn junme(&self) -> Option<u8> {
+        match self {
+            MajEvent::Reach { junme, .. } | MajEvent::Dahai { junme, .. } | MajEvent::Tsumo { junme, .. }
+            | MajEvent::Agari { junme, .. } => Some(*junme),
+            MajEvent::Naki { junme, .. } => *junme,
+            _ => None,
+        }
+    }
+
+    /// The tile drawn, discarded, called or won on, the riichi declaration tile, or the new dora marker.
+    pub fn pai(&self) -> Option<Pai> {
+        match self {
+            MajEvent::Reach { pai, .. } => *pai,
+            MajEvent::Dahai { pai, .. } | MajEvent::Tsumo { pai, .. } => Some(*pai),
+            MajEvent::Dora { dora_marker } => Some(*dora_marker),
+            MajEvent::Naki { pai, .. } => *pai,
+            MajEvent::Agari { machi, .. } => Some(*machi),
+            _ => None,
+        }
+    }
+
+    /// The player called from or dealt in.
+    pub fn target(&self) -> Option<u8> {
+        match self {
+            MajEvent::Naki { target, .. } => *target,
+            MajEvent::Agari { fromwho, .. } => Some(*fromwho),
+            _ => None,
+        }
+    }
+
+    pub fn tsumogiri(&self) -> Option<bool> {
+        match self {
+            MajEvent::Dahai { tsumogiri, .. } => Some(*tsumogiri),
+            MajEvent::Reach { tsumogiri, .. } => *tsumogiri,
+            _ => None,
+        }
+    }
+}
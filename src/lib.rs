@@ -0,0 +1,23 @@
+pub mod anonymize;
+pub mod board;
+pub mod encoding;
+pub mod enums;
+pub mod meta;
+pub mod naki;
+pub mod outcome;
+pub mod river;
+pub mod round;
+pub mod yaku;
+pub mod columnar;
+pub mod sqlite;
+pub mod stats;
+pub mod svg;
+pub mod transcript;
+pub mod validate;
+pub mod csv_tables;
+pub mod writer;
+mod utils;
+mod serialize;
+mod mjlog;
+#[cfg(test)]
+mod testing;
@@ -1,44 +1,210 @@
-mod enums;
-mod round;
-mod utils;
-mod yaku;
-mod serialize;
-
 use glob::glob;
-use std::path::Path;
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{stdout, BufWriter};
+use clap::{Parser, Subcommand, ValueEnum};
+use clap::builder::PossibleValuesParser;
 use kdam::tqdm;
+use tenhou_parser_rs::anonymize::Anonymizer;
+use tenhou_parser_rs::round;
+use tenhou_parser_rs::stats::StatsAggregator;
+use tenhou_parser_rs::svg;
+use tenhou_parser_rs::validate;
+use tenhou_parser_rs::writer::WriterRegistry;
+
+mod view;
 
 #[derive(Parser)]
-struct Args {
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    convert: ConvertArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Per-player statistics over all inputs
+    Stats(StatsArgs),
+    /// Step through the rounds of one log in the terminal
+    View(ViewArgs),
+    /// Draw the table at one event of a log as SVG
+    Svg(SvgArgs),
+    /// Replay all inputs and report broken invariants
+    Validate(ValidateArgs),
+}
+
+#[derive(clap::Args)]
+struct ConvertArgs {
+    #[arg(required = true, help = "Input file, directory or glob pattern")]
+    input: Option<String>,
+    #[arg(help = "Output file or directory")]
+    output: Option<String>,
+    #[arg(short, long, default_value = "json", value_parser = format_parser(), help = "Output format")]
+    format: String,
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "format"], help = "Write all inputs to one JSON Lines file, gzip-compressed if it ends with .gz")]
+    jsonl: Option<String>,
+    #[arg(long, help = "Keep player names percent-encoded as in the log")]
+    raw_names: bool,
+    #[arg(long, value_name = "SALT", help = "Replace player names with hashes salted with SALT")]
+    anonymize: Option<String>,
+    #[arg(long, requires = "anonymize", help = "Also clear sex and rate of anonymized players")]
+    drop_profile: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatsFormat {
+    Json,
+    Csv,
+}
+
+#[derive(clap::Args)]
+struct StatsArgs {
     #[arg(help = "Input file, directory or glob pattern")]
     input: String,
-    #[arg(help = "Output file or directory")]
+    #[arg(help = "Output file, standard output if omitted")]
+    output: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = StatsFormat::Json, help = "Output format")]
+    format: StatsFormat,
+    #[arg(long, default_value_t = 1, help = "Leave out players with fewer games")]
+    min_games: u32,
+}
+
+#[derive(clap::Args)]
+struct ViewArgs {
+    #[arg(help = "mjlog XML file, or JSON written by this tool")]
+    input: String,
+    #[arg(short, long, default_value_t = 1, help = "Round to start at, counted from 1")]
+    round: usize,
+}
+
+#[derive(clap::Args)]
+struct SvgArgs {
+    #[arg(help = "mjlog XML file, or JSON written by this tool")]
+    input: String,
+    #[arg(help = "Output file, standard output if omitted")]
     output: Option<String>,
+    #[arg(short, long, default_value_t = 1, help = "Round to draw, counted from 1")]
+    round: usize,
+    #[arg(short, long, help = "Number of events of the round to apply, the end of the round if omitted")]
+    event: Option<usize>,
+}
+
+#[derive(clap::Args)]
+struct ValidateArgs {
+    #[arg(help = "Input file, directory or glob pattern")]
+    input: String,
+}
+
+fn format_parser() -> PossibleValuesParser {
+    PossibleValuesParser::new(WriterRegistry::default().entries().iter().map(|x| {
+        clap::builder::PossibleValue::new(x.name).help(x.help)
+    }))
+}
+
+fn input_paths(input: &str) -> Vec<PathBuf> {
+    let input_glob = if Path::is_dir(Path::new(input)) {
+        format!("{}/*.xml", input)
+    } else {
+        input.to_string()
+    };
+    glob(&input_glob).unwrap().map(|x| x.unwrap()).collect()
+}
+
+fn log_id(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().into_owned()
 }
 
 fn main() {
     stderrlog::new().module(module_path!()).init().unwrap();
-    let args = Args::parse();
-    if Path::is_file(Path::new(&args.input)) {
-        let output = Path::new(&args.output.unwrap_or(args.input.clone())).with_extension("json");
-        round::Game::parse_xml_file(&args.input).write_to_json(output);
-        log::info!("Done");
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Stats(args)) => stats(args),
+        Some(Command::View(args)) => view::view(&view::load_game(&args.input), args.round),
+        Some(Command::Svg(args)) => svg(args),
+        Some(Command::Validate(args)) => validate(args),
+        None => convert(cli.convert),
+    }
+}
+
+fn stats(args: StatsArgs) {
+    let mut aggregator = StatsAggregator::default();
+    tqdm!(input_paths(&args.input).iter()).for_each(|path| {
+        aggregator.add(&round::Game::parse_xml_file(path));
+    });
+    let writer: Box<dyn std::io::Write> = match &args.output {
+        Some(output) => Box::new(BufWriter::new(File::create(output).unwrap())),
+        None => Box::new(stdout().lock()),
+    };
+    match args.format {
+        StatsFormat::Json => aggregator.write_json(writer, args.min_games),
+        StatsFormat::Csv => aggregator.write_csv(writer, args.min_games),
+    }
+}
+
+fn svg(args: SvgArgs) {
+    let game = view::load_game(&args.input);
+    let Some(round) = args.round.checked_sub(1).filter(|&x| x < game.games.len()) else {
+        log::error!("Round {} is not in the log, which has {} rounds", args.round, game.games.len());
         return;
+    };
+    let events = game.games[round].game.len();
+    let svg = svg::board_svg(&game, round, args.event.unwrap_or(events).min(events));
+    match &args.output {
+        Some(output) => std::fs::write(output, svg).unwrap(),
+        None => print!("{}", svg),
     }
-    let (input_glob, output_dir) = if Path::is_dir(Path::new(&args.input)) {
-        (format!("{}/*.xml", args.input), args.output.unwrap_or(args.input))
-    } else {
-        if args.output.is_none() {
-            log::error!("Output directory is required when input is a glob pattern");
+}
+
+fn validate(args: ValidateArgs) {
+    let paths = input_paths(&args.input);
+    let mut invalid = 0;
+    for path in &paths {
+        let violations = validate::validate(&round::Game::parse_xml_file(path));
+        for violation in &violations {
+            println!("{}: {}", path.display(), violation);
+        }
+        invalid += !violations.is_empty() as usize;
+    }
+    log::info!("{} of {} logs have violations", invalid, paths.len());
+    if invalid > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn convert(args: ConvertArgs) {
+    let input = args.input.unwrap();
+    let registry = WriterRegistry::default();
+    let (entry, output) = match args.jsonl {
+        Some(jsonl) => (registry.get("jsonl").unwrap(), Some(jsonl)),
+        None => (registry.get(&args.format).unwrap(), args.output),
+    };
+    let output = match output {
+        Some(output) => output,
+        None if entry.per_input && Path::new(&input).exists() => input.clone(),
+        None => {
+            log::error!("Output is required for the {} format or when input is a glob pattern", entry.name);
             return;
         }
-        (args.input.clone(), args.output.unwrap_or(args.input))
     };
-    let input = glob(&input_glob).unwrap().map(|x| x.unwrap()).collect::<Vec<_>>();
-    tqdm!(input.iter()).for_each(|path| {
-        let path = path.as_path();
-        let output = Path::new(&output_dir).join(path.file_stem().unwrap()).with_extension("json");
-        round::Game::parse_xml_file(path).write_to_json(output);
-    });
-}
\ No newline at end of file
+    let options = round::ParseOptions { raw_names: args.raw_names };
+    let anonymizer = args.anonymize.map(|salt| Anonymizer::new(&salt, args.drop_profile));
+    let mut writer = (entry.open)(Path::new(&output));
+    let skipped = tqdm!(input_paths(&input).iter()).filter(|path| {
+        let log_id = log_id(path);
+        if writer.contains(&log_id) {
+            return true;
+        }
+        let mut game = round::Game::parse_xml_file_with(path, options);
+        if let Some(anonymizer) = &anonymizer {
+            anonymizer.apply(&mut game);
+        }
+        writer.write(&log_id, &game);
+        false
+    }).count();
+    writer.finish();
+    if skipped > 0 {
+        log::info!("Skipped {} already written logs", skipped);
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// Identity of a game taken from its Tenhou log id, e.g. `2023010100gm-00a9-0000-0a1b2c3d`:
+/// the start hour in JST, the game type in hex, the lobby code and a hash.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameMeta {
+    pub log_id: String,
+    /// `yyyy-mm-dd` in JST.
+    pub date: String,
+    pub hour: u8,
+    /// Lobby code of the room, `0000` outside of private lobbies.
+    pub room: String,
+}
+
+const LOG_ID_LEN: usize = 31;
+
+fn is_log_id(s: &[u8]) -> bool {
+    s.iter().enumerate().all(|(i, &c)| match i {
+        0..=9 | 18..=21 => c.is_ascii_digit(),
+        10 => c == b'g',
+        11 => c == b'm',
+        12 | 17 | 22 => c == b'-',
+        _ => c.is_ascii_hexdigit(),
+    })
+}
+
+impl GameMeta {
+    /// Finds a log id anywhere in `s`, which can be a file name or a replay URL such as
+    /// `https://tenhou.net/0/?log=2023010100gm-00a9-0000-0a1b2c3d&tw=0`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let log_id = s.as_bytes().windows(LOG_ID_LEN).position(is_log_id).map(|i| &s[i..i + LOG_ID_LEN])?;
+        Some(Self {
+            log_id: log_id.to_string(),
+            date: format!("{}-{}-{}", &log_id[0..4], &log_id[4..6], &log_id[6..8]),
+            hour: log_id[8..10].parse().unwrap(),
+            room: log_id[18..22].to_string(),
+        })
+    }
+}
@@ -0,0 +1,267 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use quick_xml::Writer;
+use crate::enums::{MajEvent, NakiKind, Pai, ReachStep, RyuukyokuReason};
+use crate::naki::encode_naki;
+use crate::round::{Game, Round};
+use crate::utils::encode_name;
+use crate::yaku::yaku_han;
+
+fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")
+}
+
+fn join_pai<'a>(pais: impl IntoIterator<Item = &'a Pai>) -> String {
+    join(pais.into_iter().map(|&x| u8::from(x)))
+}
+
+/// `before0,delta0,before1,delta1,...` in units of 100
+fn join_sc(scores: &[i32; 4], deltas: &[i32; 4]) -> String {
+    join((0..4).flat_map(|i| [scores[i] / 100, deltas[i] / 100]))
+}
+
+fn limit(han: u8, hu: u8) -> u8 {
+    match han {
+        13.. => 5,
+        11..=12 => 4,
+        8..=10 => 3,
+        6..=7 => 2,
+        5 => 1,
+        4 if hu >= 40 => 1,
+        3 if hu >= 70 => 1,
+        _ => 0,
+    }
+}
+
+/// Rebuilds the `yaku` or `yakuman` attribute from the flattened yaku list of an `Agari`.
+fn yaku_attribute(yaku: &[u8], naki: &Option<Vec<MajEvent>>, has_ura: bool) -> (&'static str, String) {
+    if !yaku.is_empty() && yaku.iter().all(|&x| (36..=51).contains(&x)) {
+        return ("yakuman", join(yaku));
+    }
+    let menzen = naki.iter().flatten().all(|x| matches!(x, MajEvent::Naki { kind: NakiKind::Ankan | NakiKind::Nukidora, .. }));
+    let mut pairs: Vec<(u8, u8)> = Vec::new();
+    for &y in yaku {
+        match pairs.iter_mut().find(|(x, _)| *x == y) {
+            Some((_, val)) => *val += 1,
+            None => pairs.push((y, if (52..=54).contains(&y) { 1 } else { yaku_han(y, menzen) })),
+        }
+    }
+    if has_ura && !yaku.contains(&53) {
+        pairs.push((53, 0));
+    }
+    ("yaku", join(pairs.iter().flat_map(|&(y, val)| [y, val])))
+}
+
+impl Game {
+    /// The name of a seat as in the UN tag, which `id` already is if parsed with `raw_names`.
+    fn xml_name(&self, seat: usize) -> String {
+        if self.raw_names { self.id[seat].clone() } else { encode_name(&self.id[seat]) }
+    }
+
+    fn write_round<W: Write>(&self, writer: &mut Writer<W>, round: &Round, last: bool) {
+        let data = &round.data;
+        let bakaze = match data.bakaze.as_str() {
+            "E" => 0,
+            "S" => 1,
+            "W" => 2,
+            _ => 3,
+        };
+        let mut scores = data.scores;
+        let mut kyotaku = data.kyotaku;
+        let mut init = BytesStart::new("INIT");
+        init.push_attribute(("seed", join([bakaze * 4 + data.kyoku - 1, data.honba, data.kyotaku, data.dice[0], data.dice[1], u8::from(data.dora_marker)]).as_str()));
+        init.push_attribute(("ten", join(scores.map(|x| x / 100)).as_str()));
+        init.push_attribute(("oya", data.oya.to_string().as_str()));
+        for (i, tehai) in data.tehais.iter().enumerate() {
+            init.push_attribute((format!("hai{}", i).as_str(), join_pai(tehai).as_str()));
+        }
+        writer.write_event(Event::Empty(init)).unwrap();
+
+        let final_event = round.game.iter().rposition(|x| matches!(x, MajEvent::Agari { .. } | MajEvent::Ryuukyoku { .. }));
+        for (i, event) in round.game.iter().enumerate() {
+            let mut tag = match event {
+                MajEvent::Unknown | MajEvent::Init { .. } => continue,
+                MajEvent::Tsumo { actor, pai, .. } => {
+                    BytesStart::new(format!("{}{}", ['T', 'U', 'V', 'W'][*actor as usize], u8::from(*pai)))
+                }
+                MajEvent::Dahai { actor, pai, .. } => {
+                    BytesStart::new(format!("{}{}", ['D', 'E', 'F', 'G'][*actor as usize], u8::from(*pai)))
+                }
+                MajEvent::Naki { actor, .. } => {
+                    let mut tag = BytesStart::new("N");
+                    tag.push_attribute(("who", actor.to_string().as_str()));
+                    tag.push_attribute(("m", encode_naki(event).to_string().as_str()));
+                    tag
+                }
+                MajEvent::Dora { dora_marker } => {
+                    let mut tag = BytesStart::new("DORA");
+                    tag.push_attribute(("hai", u8::from(*dora_marker).to_string().as_str()));
+                    tag
+                }
+                MajEvent::Reach { actor, step, scores: deposited, .. } => {
+                    let mut tag = BytesStart::new("REACH");
+                    tag.push_attribute(("who", actor.to_string().as_str()));
+                    if *step == ReachStep::RiichiAccepted {
+                        scores[*actor as usize] -= 1000;
+                        scores = deposited.unwrap_or(scores);
+                        kyotaku += 1;
+                        tag.push_attribute(("ten", join(scores.map(|x| x / 100)).as_str()));
+                    }
+                    tag.push_attribute(("step", if *step == ReachStep::Riichi { "1" } else { "2" }));
+                    tag
+                }
+                MajEvent::Agari { honba, kyotaku, hai, naki, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas, .. } => {
+                    let mut tag = BytesStart::new("AGARI");
+                    tag.push_attribute(("ba", join([honba, kyotaku]).as_str()));
+                    tag.push_attribute(("hai", join_pai(hai).as_str()));
+                    if let Some(naki) = naki {
+                        tag.push_attribute(("m", join(naki.iter().map(encode_naki)).as_str()));
+                    }
+                    tag.push_attribute(("machi", u8::from(*machi).to_string().as_str()));
+                    tag.push_attribute(("ten", join([*hu as i32, *score, limit(*han, *hu) as i32]).as_str()));
+                    let (key, value) = yaku_attribute(yaku, naki, ura_marker.is_some());
+                    tag.push_attribute((key, value.as_str()));
+                    tag.push_attribute(("doraHai", join_pai(dora_marker).as_str()));
+                    if let Some(ura_marker) = ura_marker {
+                        tag.push_attribute(("doraHaiUra", join_pai(ura_marker).as_str()));
+                    }
+                    tag.push_attribute(("who", actor.to_string().as_str()));
+                    tag.push_attribute(("fromWho", fromwho.to_string().as_str()));
+                    if let Some(paowho) = paowho {
+                        tag.push_attribute(("paoWho", paowho.to_string().as_str()));
+                    }
+                    tag.push_attribute(("sc", join_sc(&scores, deltas).as_str()));
+                    (0..4).for_each(|i| scores[i] += deltas[i]);
+                    tag
+                }
+                MajEvent::Disconnect { actor } => {
+                    let mut tag = BytesStart::new("BYE");
+                    tag.push_attribute(("who", actor.to_string().as_str()));
+                    tag
+                }
+                MajEvent::Reconnect { actor } => {
+                    let mut tag = BytesStart::new("UN");
+                    tag.push_attribute((format!("n{}", actor).as_str(), self.xml_name(*actor as usize).as_str()));
+                    tag
+                }
+                MajEvent::Ryuukyoku { reason, tehais, deltas, .. } => {
+                    let mut tag = BytesStart::new("RYUUKYOKU");
+                    if *reason != RyuukyokuReason::Howanpai {
+                        tag.push_attribute(("type", reason.to_string().as_str()));
+                    }
+                    tag.push_attribute(("ba", join([data.honba, kyotaku]).as_str()));
+                    tag.push_attribute(("sc", join_sc(&scores, deltas).as_str()));
+                    for (i, tehai) in tehais.iter().enumerate() {
+                        if let Some(tehai) = tehai {
+                            tag.push_attribute((format!("hai{}", i).as_str(), join_pai(tehai).as_str()));
+                        }
+                    }
+                    (0..4).for_each(|i| scores[i] += deltas[i]);
+                    tag
+                }
+            };
+            if last && Some(i) == final_event {
+                tag.push_attribute(("owari", join((0..4).flat_map(|i| [self.owari[i].to_string(), format!("{:.1}", self.uma[i])])).as_str()));
+            }
+            writer.write_event(Event::Empty(tag)).unwrap();
+        }
+    }
+
+    /// Writes the game as mjlog 2.3 XML, the inverse of `parse_xml_file`.
+    pub fn write_xml<W: Write>(&self, writer: W) {
+        let mut writer = Writer::new(writer);
+        let mut root = BytesStart::new("mjloggm");
+        root.push_attribute(("ver", "2.3"));
+        writer.write_event(Event::Start(root)).unwrap();
+
+        let mut go = BytesStart::new("GO");
+        go.push_attribute(("type", self.r#type.to_string().as_str()));
+        go.push_attribute(("lobby", self.lobby.unwrap_or(0).to_string().as_str()));
+        writer.write_event(Event::Empty(go)).unwrap();
+
+        let mut un = BytesStart::new("UN");
+        for i in 0..4 {
+            un.push_attribute((format!("n{}", i).as_str(), self.xml_name(i).as_str()));
+        }
+        un.push_attribute(("dan", join(self.dan.map(u8::from)).as_str()));
+        un.push_attribute(("rate", join(self.rate.map(|x| format!("{:.2}", x))).as_str()));
+        un.push_attribute(("sx", self.sex.join(",").as_str()));
+        writer.write_event(Event::Empty(un)).unwrap();
+
+        let mut taikyoku = BytesStart::new("TAIKYOKU");
+        taikyoku.push_attribute(("oya", "0"));
+        writer.write_event(Event::Empty(taikyoku)).unwrap();
+
+        for (i, round) in self.games.iter().enumerate() {
+            self.write_round(&mut writer, round, i + 1 == self.games.len());
+        }
+        writer.write_event(Event::End(BytesEnd::new("mjloggm"))).unwrap();
+    }
+
+    pub fn write_to_xml<P: AsRef<Path>>(&self, path: P) {
+        let mut writer = BufWriter::new(File::create(path).unwrap());
+        self.write_xml(&mut writer);
+        writer.flush().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+    use crate::round::{Game, ParseOptions};
+    use crate::testing::fixture;
+
+    fn to_xml(game: &Game) -> Vec<u8> {
+        let mut xml = Vec::new();
+        game.write_xml(&mut xml);
+        xml
+    }
+
+    fn parse(xml: &[u8]) -> Game {
+        Game::parse_reader(Reader::from_reader(xml), ParseOptions::default())
+    }
+
+    #[test]
+    fn parse_of_written_xml_equals_parse_of_log() {
+        let game = fixture();
+        let xml = to_xml(&game);
+        let mut read = parse(&xml);
+        read.meta = game.meta.clone();
+        assert_eq!(serde_json::to_string(&read).unwrap(), serde_json::to_string(&game).unwrap());
+        assert_eq!(to_xml(&read), xml);
+    }
+
+    fn values(text: &str, attribute: &str) -> Vec<String> {
+        text.match_indices(&format!("{}=\"", attribute)).map(|(i, x)| {
+            let rest = &text[i + x.len()..];
+            rest[..rest.find('"').unwrap()].to_string()
+        }).collect()
+    }
+
+    #[test]
+    fn names_are_encoded_unless_parsed_raw() {
+        let mut game = fixture();
+        game.id[0] = "%41%42".to_string();
+        assert_eq!(parse(&to_xml(&game)).id[0], "%41%42");
+
+        let raw = Game::parse_xml_file_with(crate::testing::FIXTURE, ParseOptions { raw_names: true });
+        let xml = String::from_utf8(to_xml(&raw)).unwrap();
+        assert!(xml.contains(r#"n0="%41" n1="%E3%81%82" n2="NoName" n3="%42%43""#));
+    }
+
+    #[test]
+    fn attributes_are_written_back() {
+        let xml = String::from_utf8(to_xml(&fixture())).unwrap();
+        let log = std::fs::read_to_string(crate::testing::FIXTURE).unwrap();
+        assert_eq!(values(&xml, "<INIT seed"), values(&log, "<INIT seed"));
+        assert_eq!(values(&xml, " owari"), values(&log, " owari"));
+
+        // Every meld comes back bit for bit, in calls and in the melds of wins.
+        let melds = |text: &str| values(text, " m").iter().flat_map(|x| x.split(',').map(|m| m.parse().unwrap())).collect::<Vec<u32>>();
+        let logged = melds(&log);
+        assert!(logged.iter().any(|m| m & 4 != 0) && logged.iter().any(|m| m & 16 != 0) && logged.iter().any(|m| m & 0x3f == 0));
+        assert_eq!(melds(&xml), logged);
+    }
+}
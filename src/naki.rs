@@ -0,0 +1,204 @@
+//! Codec for the 16-bit `m` attribute of `N` tags and of the `m` list of `AGARI`.
+
+use crate::enums::{MajEvent, NakiKind, Pai};
+
+/// Decodes a meld. `junme` is `None` for the melds listed in `AGARI`.
+pub fn decode_naki(actor: u8, m: u32, junme: Option<u8>) -> MajEvent {
+    if m & 4 != 0 {
+        //chii
+        let tile_detail = [(m >> 3) & 3, (m >> 5) & 3, (m >> 7) & 3];
+        let block1 = m >> 10;
+        let called = block1 % 3;
+        let base = (block1 / 21) * 8 + (block1 / 3) * 4;
+        let target = (actor + 3) % 4;
+        let consumed_hai = tile_detail[called as usize] + 4 * called + base;
+        let hai = Pai::from(consumed_hai as u8);
+        let consumed_num = (0..3).filter(|&i| i != called).map(|i| (tile_detail[i as usize] + 4 * i + base) as u8).collect::<Vec<u8>>();
+        let consumed = consumed_num.iter().map(|&x: &u8| Pai::from(x)).collect();
+        MajEvent::Naki {
+            junme,
+            actor,
+            consumed,
+            pai: Some(hai),
+            target: Some(target),
+            kind: NakiKind::Chii,
+        }
+    } else if m & 24 != 0 {
+        //pon
+        let tile4th = (m >> 5) & 3;
+        let target_r = m & 3;
+        let block1 = m >> 9;
+        let called = block1 % 3;
+        let base = 4 * (block1 / 3);
+        let target = (actor + target_r as u8) % 4;
+        let kind = if m & 8 != 0 { NakiKind::Pon } else { NakiKind::Kakan };
+        let pon_tile = (0..4).filter(|&i| i != tile4th).map(|i| (i + base) as u8).collect::<Vec<u8>>();
+        let (consumed_hai, consumed_num) = if kind == NakiKind::Pon {
+            let consumed_hai = pon_tile[called as usize];
+            let consumed_num = (0..3).filter(|&i| i != called).map(|i| pon_tile[i as usize]).collect::<Vec<u8>>();
+            (consumed_hai, consumed_num)
+        } else {
+            // The pon as it was called: the two tiles from the hand, then the called tile.
+            let consumed_hai = tile4th + base;
+            let consumed_num = (0..3).filter(|&i| i != called).chain([called]).map(|i| pon_tile[i as usize]).collect::<Vec<u8>>();
+            (consumed_hai as u8, consumed_num)
+        };
+        let consumed = consumed_num.iter().map(|&x: &u8| Pai::from(x)).collect();
+        let hai = Pai::from(consumed_hai);
+        MajEvent::Naki {
+            junme,
+            actor,
+            consumed,
+            pai: Some(hai),
+            target: Some(target),
+            kind,
+        }
+    } else if m & 32 != 0 {
+        //nukidora
+        let hai = Pai::from((m >> 8) as u8);
+        MajEvent::Naki {
+            junme,
+            actor,
+            consumed: vec![hai],
+            pai: None,
+            target: None,
+            kind: NakiKind::Nukidora,
+        }
+    } else {
+        //kan
+        let target_r = m & 3;
+        let target = (actor + target_r as u8) % 4;
+        let block1 = m >> 8;
+        let called = block1 % 4;
+        let base = 4 * (block1 / 4);
+        let consumed_num = (0..4).filter(|&i| i != called).map(|i| (i + base) as u8).collect::<Vec<u8>>();
+        let consumed_hai = called + base;
+        let hai = Pai::from(consumed_hai as u8);
+        if target == actor {
+            // The tile named by `m` goes last, like the called tile of a daiminkan.
+            let consumed = consumed_num.iter().copied().chain([consumed_hai as u8]).map(Pai::from).collect();
+            MajEvent::Naki {
+                junme,
+                actor,
+                consumed,
+                pai: None,
+                target: None,
+                kind: NakiKind::Ankan,
+            }
+        } else {
+            let consumed = consumed_num.iter().map(|&x: &u8| Pai::from(x)).collect();
+            MajEvent::Naki {
+                junme,
+                actor,
+                consumed,
+                pai: Some(hai),
+                target: Some(target),
+                kind: NakiKind::Daiminkan,
+            }
+        }
+    }
+}
+
+/// Inverse of `decode_naki`. For kakan and ankan, the last tile of `consumed` is the tile that
+/// `m` names, the called tile of the pon and the tile id of the ankan.
+pub fn encode_naki(naki: &MajEvent) -> u32 {
+    let MajEvent::Naki { actor, consumed, pai, target, kind, .. } = naki else {
+        panic!("Not a naki event: {:?}", naki);
+    };
+    let target_r = target.map(|target| (target + 4 - actor) % 4).unwrap_or(0) as u32;
+    match kind {
+        NakiKind::Chii => {
+            let hai = u8::from(pai.unwrap());
+            let mut tiles = consumed.iter().map(|&x| u8::from(x)).chain([hai]).collect::<Vec<u8>>();
+            tiles.sort();
+            let called = tiles.iter().position(|&x| x == hai).unwrap() as u32;
+            let kind34 = (tiles[0] / 4) as u32;
+            let block1 = ((kind34 / 9) * 7 + kind34 % 9) * 3 + called;
+            let tile_detail = tiles.iter().enumerate().map(|(i, &x)| ((x % 4) as u32) << (3 + 2 * i)).sum::<u32>();
+            (block1 << 10) | tile_detail | 4 | target_r
+        }
+        NakiKind::Pon | NakiKind::Kakan => {
+            let hai = u8::from(pai.unwrap());
+            let base = hai / 4 * 4;
+            let (tile4th, called) = if *kind == NakiKind::Pon {
+                let used = consumed.iter().map(|&x| u8::from(x)).chain([hai]).collect::<Vec<u8>>();
+                let tile4th = (base..base + 4).find(|x| !used.contains(x)).unwrap();
+                let called = (base..base + 4).filter(|&x| x != tile4th).position(|x| x == hai).unwrap();
+                (tile4th - base, called as u32)
+            } else {
+                let mut pon = consumed.iter().map(|&x| u8::from(x)).collect::<Vec<u8>>();
+                let called_hai = pon[2];
+                pon.sort();
+                (hai - base, pon.iter().position(|&x| x == called_hai).unwrap() as u32)
+            };
+            let block1 = (base / 4) as u32 * 3 + called;
+            let flag = if *kind == NakiKind::Pon { 8 } else { 16 };
+            (block1 << 9) | ((tile4th as u32) << 5) | flag | target_r
+        }
+        NakiKind::Ankan => (u8::from(consumed[3]) as u32) << 8,
+        NakiKind::Daiminkan => {
+            let hai = u8::from(pai.unwrap());
+            ((hai as u32) << 8) | target_r
+        }
+        NakiKind::Nukidora => {
+            let hai = u8::from(consumed[0]);
+            ((hai as u32) << 8) | 32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_naki, encode_naki};
+
+    /// Asserts that every seat decodes and encodes `m` back to itself, in calls and in `AGARI`.
+    fn round_trip(m: u32) {
+        for actor in 0..4 {
+            for junme in [None, Some(5)] {
+                assert_eq!(encode_naki(&decode_naki(actor, m, junme)), m, "actor {} m = {}", actor, m);
+            }
+        }
+    }
+
+    #[test]
+    fn chii() {
+        for block1 in 0..63 {
+            for detail in 0..64 {
+                let m = (block1 << 10) | (detail << 3) | 4 | 3;
+                round_trip(m);
+            }
+        }
+        for m in [42031, 27031, 7] {
+            round_trip(m);
+        }
+    }
+
+    #[test]
+    fn pon_and_kakan() {
+        for block1 in 0..34 * 3 {
+            for tile4th in 0..4 {
+                for target_r in 1..4 {
+                    let m = (block1 << 9) | (tile4th << 5) | target_r;
+                    round_trip(m | 8);
+                    round_trip(m | 16);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kans() {
+        for hai in 0..136 {
+            for target_r in 0..4 {
+                round_trip((hai << 8) | target_r);
+            }
+        }
+    }
+
+    #[test]
+    fn nukidora() {
+        for hai in 120..124 {
+            round_trip((hai << 8) | 32);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use serde::Serialize;
+use crate::enums::{MajEvent, Pai, RyuukyokuReason};
+use crate::round::Round;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeKind {
+    Tsumo,
+    Ron,
+    /// double or triple ron on one discard
+    MultiRon,
+    /// howanpai, including nagashi mangan
+    ExhaustiveDraw,
+    /// yao9, reach4, ron3, kan4 and kaze4
+    AbortiveDraw,
+}
+
+impl OutcomeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutcomeKind::Tsumo => "tsumo",
+            OutcomeKind::Ron => "ron",
+            OutcomeKind::MultiRon => "multi_ron",
+            OutcomeKind::ExhaustiveDraw => "exhaustive_draw",
+            OutcomeKind::AbortiveDraw => "abortive_draw",
+        }
+    }
+}
+
+/// How a round ended, derived from its `Agari` and `Ryuukyoku` events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoundOutcome {
+    pub kind: OutcomeKind,
+    /// In the order of the AGARI tags, which is the head bump order on a multiple ron.
+    pub winners: Vec<u8>,
+    /// The player who dealt in.
+    pub houjuu: Option<u8>,
+    /// The dealer keeps the seat: a dealer win, a tenpai dealer at an exhaustive draw or an abortive draw.
+    pub renchan: bool,
+    /// Honba of the next round.
+    pub honba: u8,
+}
+
+/// The AGARI tags of one win: a tsumo, a ron, or consecutive rons on the same discard.
+#[derive(Debug)]
+pub struct Hora<'a> {
+    /// The discarder on a ron, the winner on a tsumo.
+    pub fromwho: u8,
+    /// The tile won on, the shared discard on a multiple ron.
+    pub pai: Pai,
+    /// `Agari` events in head bump order, the order of the AGARI tags.
+    /// Only the first winner takes the honba and the riichi sticks.
+    pub agari: Vec<&'a MajEvent>,
+    /// Indices of the `Agari` events in `Round::game`.
+    pub seq: Vec<usize>,
+}
+
+impl Hora<'_> {
+    pub fn is_tsumo(&self) -> bool {
+        self.agari[0].actor() == Some(self.fromwho)
+    }
+
+    pub fn is_multi_ron(&self) -> bool {
+        self.agari.len() > 1
+    }
+
+    pub fn winners(&self) -> Vec<u8> {
+        self.agari.iter().filter_map(|x| x.actor()).collect()
+    }
+
+    /// Whether the score changes give the honba and riichi sticks of the first tag to the first winner
+    /// and nothing on top of the hand value to the others.
+    pub fn sticks_to_first_winner(&self) -> bool {
+        let MajEvent::Agari { honba, kyotaku, .. } = self.agari[0] else {
+            unreachable!()
+        };
+        let sticks = *honba as i32 * 300 + *kyotaku as i32 * 1000;
+        self.agari.iter().enumerate().all(|(i, event)| {
+            let MajEvent::Agari { actor, score, deltas, .. } = event else {
+                unreachable!()
+            };
+            deltas[*actor as usize] - score == if i == 0 { sticks } else { 0 }
+        })
+    }
+}
+
+impl Round {
+    /// Groups the `Agari` events. AGARI tags follow each other on a multiple ron.
+    pub fn horas(&self) -> Vec<Hora<'_>> {
+        let mut horas: Vec<Hora> = Vec::new();
+        let mut previous_agari = false;
+        for (seq, event) in self.game.iter().enumerate() {
+            let MajEvent::Agari { actor, fromwho, machi, .. } = event else {
+                previous_agari = false;
+                continue;
+            };
+            match horas.last_mut() {
+                Some(hora) if previous_agari && actor != fromwho && !hora.is_tsumo() && hora.fromwho == *fromwho => {
+                    hora.agari.push(event);
+                    hora.seq.push(seq);
+                }
+                _ => horas.push(Hora { fromwho: *fromwho, pai: *machi, agari: vec![event], seq: vec![seq] }),
+            }
+            previous_agari = true;
+        }
+        horas
+    }
+
+    /// Position of every event in the head bump order of its `Hora`, 0 for the winner who takes
+    /// the honba and riichi sticks, and `None` for events other than `Agari`.
+    pub fn hora_orders(&self) -> Vec<Option<u8>> {
+        let mut orders = vec![None; self.game.len()];
+        for hora in self.horas() {
+            for (order, &seq) in hora.seq.iter().enumerate() {
+                orders[seq] = Some(order as u8);
+            }
+        }
+        orders
+    }
+
+    /// `None` if the log ends before the round does.
+    pub fn outcome(&self) -> Option<RoundOutcome> {
+        let oya = self.data.oya;
+        if let Some(hora) = self.horas().first() {
+            let kind = if hora.is_multi_ron() {
+                OutcomeKind::MultiRon
+            } else if hora.is_tsumo() {
+                OutcomeKind::Tsumo
+            } else {
+                OutcomeKind::Ron
+            };
+            let winners = hora.winners();
+            let renchan = winners.contains(&oya);
+            let honba = if renchan { self.data.honba + 1 } else { 0 };
+            let houjuu = (!hora.is_tsumo()).then_some(hora.fromwho);
+            return Some(RoundOutcome { kind, winners, houjuu, renchan, honba });
+        }
+        self.game.iter().find_map(|event| {
+            let MajEvent::Ryuukyoku { reason, tenpai, .. } = event else {
+                return None;
+            };
+            let (kind, renchan) = match reason {
+                RyuukyokuReason::Howanpai | RyuukyokuReason::Nm => (OutcomeKind::ExhaustiveDraw, tenpai[oya as usize]),
+                _ => (OutcomeKind::AbortiveDraw, true),
+            };
+            Some(RoundOutcome { kind, winners: Vec::new(), houjuu: None, renchan, honba: self.data.honba + 1 })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutcomeKind, RoundOutcome};
+    use crate::enums::RyuukyokuReason;
+    use crate::round::Round;
+    use crate::testing::{fixture, RoundBuilder};
+
+    const TEHAIS: [&str; 4] = ["123456789m1234p", "123456789p1234s", "123456789s1234m", "1112223334445z"];
+
+    /// Seat 1 deals in its second honba.
+    fn round(build: impl FnOnce(&mut RoundBuilder)) -> Round {
+        let mut builder = RoundBuilder::new(1, TEHAIS);
+        build(&mut builder);
+        let mut round = builder.build();
+        round.data.honba = 2;
+        round
+    }
+
+    fn draw(tenpai: [bool; 4], deltas: [i32; 4]) -> Round {
+        round(|b| b.ryuukyoku(RyuukyokuReason::Howanpai, tenpai, deltas))
+    }
+
+    #[test]
+    fn tenpai_dealer_keeps_the_seat_at_an_exhaustive_draw() {
+        let expected = RoundOutcome { kind: OutcomeKind::ExhaustiveDraw, winners: vec![], houjuu: None, renchan: true, honba: 3 };
+        assert_eq!(draw([false, true, false, false], [-1000, 3000, -1000, -1000]).outcome(), Some(expected));
+    }
+
+    #[test]
+    fn noten_dealer_passes_the_seat_and_the_honba_increments() {
+        let outcome = draw([true, false, false, false], [3000, -1000, -1000, -1000]).outcome().unwrap();
+        assert!(!outcome.renchan);
+        assert_eq!(outcome.honba, 3);
+    }
+
+    #[test]
+    fn abortive_draw_keeps_the_seat() {
+        let outcome = round(|b| b.ryuukyoku(RyuukyokuReason::Kaze4, [false; 4], [0; 4])).outcome().unwrap();
+        assert_eq!((outcome.kind, outcome.renchan, outcome.honba), (OutcomeKind::AbortiveDraw, true, 3));
+    }
+
+    #[test]
+    fn non_dealer_win_passes_the_seat_and_clears_the_honba() {
+        let round = round(|b| {
+            let pai = b.tsumo(0, "7z");
+            b.dahai(0, pai);
+            b.agari(2, 0, pai, 1000, [-1600, 0, 1600, 0]);
+        });
+        let expected = RoundOutcome { kind: OutcomeKind::Ron, winners: vec![2], houjuu: Some(0), renchan: false, honba: 0 };
+        assert_eq!(round.outcome(), Some(expected));
+    }
+
+    #[test]
+    fn dealer_win_keeps_the_seat() {
+        let round = round(|b| {
+            let pai = b.tsumo(1, "5s");
+            b.agari(1, 1, pai, 1500, [-700, 2100, -700, -700]);
+        });
+        let expected = RoundOutcome { kind: OutcomeKind::Tsumo, winners: vec![1], houjuu: None, renchan: true, honba: 3 };
+        assert_eq!(round.outcome(), Some(expected));
+    }
+
+    #[test]
+    fn unfinished_round_has_no_outcome() {
+        assert_eq!(round(|b| { b.tsumo(1, "5s"); }).outcome(), None);
+    }
+
+    #[test]
+    fn double_rons_of_the_fixture_are_one_hora_each() {
+        let game = fixture();
+        let rounds = game.games.iter().filter(|x| x.horas().iter().any(|x| x.is_multi_ron())).collect::<Vec<_>>();
+        let horas = rounds.iter().map(|x| {
+            let horas = x.horas();
+            assert_eq!(horas.len(), 1);
+            assert!(horas[0].sticks_to_first_winner());
+            (horas[0].fromwho, horas[0].winners())
+        }).collect::<Vec<_>>();
+        assert_eq!(horas, [(3, vec![0, 2]), (2, vec![0, 1])]);
+
+        let round = rounds[1];
+        let hora = &round.horas()[0];
+        assert_eq!(hora.seq.iter().map(|&x| round.hora_orders()[x]).collect::<Vec<_>>(), [Some(0), Some(1)]);
+        // The dealer of South 2 is the second winner and keeps the seat.
+        assert_eq!((round.data.oya, round.data.honba), (1, 0));
+        let expected = RoundOutcome { kind: OutcomeKind::MultiRon, winners: vec![0, 1], houjuu: Some(2), renchan: true, honba: 1 };
+        assert_eq!(round.outcome(), Some(expected));
+
+        let json = serde_json::to_value(round).unwrap();
+        assert_eq!(json["horas"], serde_json::json!([{ "fromwho": 2, "pai": hora.pai, "winners": [0, 1], "seq": hora.seq }]));
+    }
+
+    #[test]
+    fn sticks_paid_to_the_second_winner_are_found() {
+        let round = round(|b| {
+            let pai = b.tsumo(0, "7z");
+            b.dahai(0, pai);
+            b.agari(2, 0, pai, 1000, [-2000, 0, 1000, 1000]);
+            b.agari(3, 0, pai, 1000, [0; 4]);
+        });
+        let horas = round.horas();
+        assert!(horas[0].is_multi_ron());
+        assert!(!horas[0].sticks_to_first_winner());
+    }
+}
@@ -0,0 +1,112 @@
+use serde::Serialize;
+use crate::enums::{MajEvent, NakiKind, Pai, ReachStep};
+use crate::round::Round;
+
+/// A discarded tile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RiverTile {
+    pub pai: Pai,
+    pub junme: u8,
+    pub tsumogiri: bool,
+    /// The riichi declaration tile.
+    pub riichi: bool,
+    /// Placed sideways: the declaration tile, or the next discard if the declaration tile was called away.
+    pub sideways: bool,
+    /// The player who called the tile away and how.
+    pub called: Option<(u8, NakiKind)>,
+}
+
+/// The discards of one player in order, including the ones called away.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct River {
+    pub tiles: Vec<RiverTile>,
+}
+
+impl River {
+    /// The tiles left in front of the player.
+    pub fn visible(&self) -> impl Iterator<Item = &RiverTile> {
+        self.tiles.iter().filter(|x| x.called.is_none())
+    }
+}
+
+/// Rivers of the four players, built one event at a time.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Rivers {
+    pub(crate) rivers: [River; 4],
+    declared: [bool; 4],
+    sideways_pending: [bool; 4],
+}
+
+impl Rivers {
+    pub(crate) fn apply(&mut self, event: &MajEvent) {
+        match event {
+            MajEvent::Reach { actor, step: ReachStep::Riichi, .. } => {
+                self.declared[*actor as usize] = true;
+            }
+            MajEvent::Dahai { junme, actor, pai, tsumogiri } => {
+                let actor = *actor as usize;
+                let riichi = std::mem::take(&mut self.declared[actor]);
+                self.rivers[actor].tiles.push(RiverTile {
+                    pai: *pai,
+                    junme: *junme,
+                    tsumogiri: *tsumogiri,
+                    riichi,
+                    sideways: riichi || std::mem::take(&mut self.sideways_pending[actor]),
+                    called: None,
+                });
+            }
+            MajEvent::Naki { actor, pai: Some(pai), target: Some(target), kind, .. } if *kind != NakiKind::Kakan => {
+                let target = *target as usize;
+                if let Some(tile) = self.rivers[target].tiles.last_mut().filter(|x| x.pai == *pai) {
+                    tile.called = Some((*actor, *kind));
+                    if tile.sideways {
+                        self.sideways_pending[target] = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Round {
+    pub fn rivers(&self) -> [River; 4] {
+        self.rivers_until(self.game.len())
+    }
+
+    /// The rivers before the event at `end`.
+    pub fn rivers_until(&self, end: usize) -> [River; 4] {
+        let mut rivers = Rivers::default();
+        for event in &self.game[..end] {
+            rivers.apply(event);
+        }
+        rivers.rivers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enums::NakiKind;
+    use crate::testing::RoundBuilder;
+
+    #[test]
+    fn sideways_tile_moves_on_when_the_riichi_tile_is_called() {
+        let mut b = RoundBuilder::new(0, ["123456789m1234p", "123456789p1177z", "123456789s1234m", "2223334445556z"]);
+        let declared = b.tsumo(0, "7z");
+        b.riichi(0, declared);
+        b.naki(1, NakiKind::Pon, &b.dealt(1, "77z"), Some(declared), Some(0));
+        b.dahai(1, b.dealt(1, "1p")[0]);
+        let next = b.tsumo(0, "9s");
+        b.dahai(0, next);
+        let rivers = b.build().rivers();
+
+        let river = &rivers[0].tiles;
+        assert_eq!(river.len(), 2);
+        assert!(river[0].riichi && river[0].sideways && river[0].tsumogiri);
+        assert_eq!(river[0].called, Some((1, NakiKind::Pon)));
+        assert!(!river[1].riichi && river[1].sideways);
+        let visible = rivers[0].visible().map(|x| x.pai).collect::<Vec<_>>();
+        assert_eq!(visible, [next]);
+        assert!(!rivers[1].tiles[0].sideways);
+    }
+}
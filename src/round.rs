@@ -3,22 +3,39 @@ use std::path::Path;
 use log::warn;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
-use crate::enums::{MajEvent, Pai};
-use crate::utils::{GetAttribute, IntoActor, IntoNumVec, IntoPaiVec};
-use serde::{Serialize};
+use crate::encoding::Encoding;
+use crate::enums::{Dan, MajEvent, Pai, ReachStep, RyuukyokuReason, ANONYMOUS};
+use crate::meta::GameMeta;
+use crate::naki::decode_naki;
+use crate::utils::{decode_name, GetAttribute, IntoActor, IntoNumVec, IntoPaiVec};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ParseOptions {
+    /// Keep player names percent-encoded as in the UN tag instead of decoding them to UTF-8.
+    pub raw_names: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Game {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<GameMeta>,
     pub r#type: u16,
     pub lobby: Option<u16>,
     pub id: [String; 4],
-    pub dan: [u8; 4],
+    /// `id` holds the names percent-encoded as in the log, from parsing with `raw_names`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub raw_names: bool,
+    pub dan: [Dan; 4],
     pub rate: [f32; 4],
     pub sex: [String; 4],
     pub games: Vec<Round>,
     pub owari: [i32; 4],
+    /// Final points after uma, the second value of each pair in `owari`.
+    #[serde(default)]
+    pub uma: [f32; 4],
 }
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RoundData {
     pub bakaze: String,
     pub dora_marker: Pai,
@@ -28,118 +45,61 @@ pub struct RoundData {
     pub oya: u8,
     pub scores: [i32; 4],
     pub tehais: [[Pai; 13]; 4],
+    /// The two dice of the `seed` attribute.
+    #[serde(default)]
+    pub dice: [u8; 2],
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default)]
 pub struct Round {
-    #[serde(skip)]
     pub junme: [u8; 4],
-    #[serde(skip)]
     pub last_draw: Option<Pai>,
     pub data: RoundData,
     pub game: Vec<MajEvent>,
 }
 
-impl Game {
-    fn parse_naki(actor: u8, m: u32, junme: Option<u8>) -> MajEvent {
-        if m & 4 != 0 {
-            //chii
-            let tile_detail = [(m >> 3) & 3, (m >> 5) & 3, (m >> 7) & 3];
-            let block1 = m >> 10;
-            let called = block1 % 3;
-            let base = (block1 / 21) * 8 + (block1 / 3) * 4;
-            let target = (actor + 3) % 4;
-            let consumed_hai = tile_detail[called as usize] + 4 * called + base;
-            let hai = Pai::from(consumed_hai as u8);
-            let consumed_num = (0..3).filter(|&i| i != called).map(|i| (tile_detail[i as usize] + 4 * i + base) as u8).collect::<Vec<u8>>();
-            let consumed = consumed_num.iter().map(|&x: &u8| Pai::from(x)).collect();
-            MajEvent::Naki {
-                junme,
-                actor,
-                consumed,
-                pai: Some(hai),
-                target: Some(target),
-                r#type: "chii",
-            }
-        } else if m & 24 != 0 {
-            //pon
-            let tile4th = (m >> 5) & 3;
-            let target_r = m & 3;
-            let block1 = m >> 9;
-            let called = block1 % 3;
-            let base = 4 * (block1 / 3);
-            let target = (actor + target_r as u8) % 4;
-            let r#type = if m & 8 != 0 { "pon" } else { "kakan" };
-            let pon_tile = (0..4).filter(|&i| i != tile4th).map(|i| (i + base) as u8).collect::<Vec<u8>>();
-            let (consumed_hai, consumed_num) = if r#type == "pon" {
-                let consumed_hai = pon_tile[called as usize];
-                let consumed_num = (0..3).filter(|&i| i != called).map(|i| pon_tile[i as usize]).collect::<Vec<u8>>();
-                (consumed_hai, consumed_num)
-            } else {
-                let consumed_hai = tile4th + base;
-                let consumed_num = pon_tile;
-                (consumed_hai as u8, consumed_num)
-            };
-            let consumed = consumed_num.iter().map(|&x: &u8| Pai::from(x)).collect();
-            let hai = Pai::from(consumed_hai);
-            MajEvent::Naki {
-                junme,
-                actor,
-                consumed,
-                pai: Some(hai),
-                target: Some(target),
-                r#type,
-            }
-        } else {
-            //kan
-            let target_r = m & 3;
-            let target = (actor + target_r as u8) % 4;
-            let block1 = m >> 8;
-            let called = block1 % 4;
-            let base = 4 * (block1 / 4);
-            let consumed_num = (0..4).filter(|&i| i != called).map(|i| (i + base) as u8).collect::<Vec<u8>>();
-            let consumed_hai = called + base;
-            let hai = Pai::from(consumed_hai as u8);
-            if target == actor {
-                let r#type = "ankan";
-                let consumed = (0..4).map(|i| i + base).map(|x| Pai::from(x as u8)).collect();
-                MajEvent::Naki {
-                    junme,
-                    actor,
-                    consumed,
-                    pai: None,
-                    target: None,
-                    r#type,
-                }
-            } else {
-                let r#type = "daiminkan";
-                let consumed = consumed_num.iter().map(|&x: &u8| Pai::from(x)).collect();
-                MajEvent::Naki {
-                    junme,
-                    actor,
-                    consumed,
-                    pai: Some(hai),
-                    target: Some(target),
-                    r#type,
-                }
+impl Round {
+    /// Riichi sticks on the table after each event, starting from the kyotaku of INIT.
+    /// Accepted riichi add a stick and the first winner takes all of them.
+    pub fn kyotaku(&self) -> Vec<u8> {
+        self.game.iter().scan(self.data.kyotaku, |kyotaku, event| {
+            match event {
+                MajEvent::Reach { step: ReachStep::RiichiAccepted, .. } => *kyotaku += 1,
+                MajEvent::Agari { .. } => *kyotaku = 0,
+                _ => {}
             }
-        }
+            Some(*kyotaku)
+        }).collect()
     }
+}
 
+impl Game {
     fn update_owari(&mut self, e: &BytesStart) {
-        if e.get_attribute("owari").is_some() {
-            self.owari = e.get_attribute("owari").unwrap().split(',').step_by(2).map(|s| s.parse().unwrap()).collect::<Vec<i32>>().try_into().unwrap();
+        if let Some(owari) = e.get_attribute("owari") {
+            self.owari = owari.split(',').step_by(2).map(|s| s.parse().unwrap()).collect::<Vec<i32>>().try_into().unwrap();
+            self.uma = owari.split(',').skip(1).step_by(2).map(|s| s.parse().unwrap()).collect::<Vec<f32>>().try_into().unwrap();
         }
     }
 
+    fn parse_deltas(e: &BytesStart) -> [i32; 4] {
+        e.get_attribute("sc").map(|x| {
+            x.into_num_vec().iter().skip(1).step_by(2).map(|&x: &i32| x * 100).collect::<Vec<i32>>().try_into().unwrap()
+        }).unwrap_or_default()
+    }
+
     fn update(&mut self, e: &BytesStart) {
         match String::from_utf8(e.name().as_ref().to_vec()).unwrap().as_str() {
             "SHUFFLE" => {}
             "GO" => {
                 self.r#type = e.get_attribute("type").unwrap().parse().unwrap();
+                self.lobby = e.get_attribute("lobby").map(|x| x.parse().unwrap());
             }
             "UN" => {
                 if e.attributes().count() < 4 {
+                    // a single name when a player reconnects
+                    if let (Some(actor), Some(game)) = ((0..4).find(|i| e.get_attribute(&format!("n{}", i)).is_some()), self.games.last_mut()) {
+                        game.game.push(MajEvent::Reconnect { actor });
+                    }
                     return;
                 }
                 self.id = [
@@ -148,7 +108,8 @@ impl Game {
                     e.get_attribute("n2").unwrap(),
                     e.get_attribute("n3").unwrap(),
                 ];
-                self.dan = e.get_attribute("dan").unwrap().into_num_vec().as_slice().try_into().unwrap();
+                let dan: [u8; 4] = e.get_attribute("dan").unwrap().into_num_vec().as_slice().try_into().unwrap();
+                self.dan = dan.map(Dan::from);
                 self.rate = e.get_attribute("rate").unwrap().into_num_vec().as_slice().try_into().unwrap();
                 self.sex = e.get_attribute("sx").unwrap().split(',').map(|s| s.to_string()).collect::<Vec<String>>().try_into().unwrap();
             }
@@ -181,6 +142,7 @@ impl Game {
                         oya,
                         scores,
                         tehais,
+                        dice: [seed[3], seed[4]],
                     },
                     game: Vec::new(),
                 });
@@ -197,7 +159,6 @@ impl Game {
                     junme: game.junme[actor as usize],
                     actor,
                     pai,
-                    r#type: "tsumo",
                 });
             }
             t if b"DEFG".contains(&t.as_bytes()[0]) && e.attributes().count() == 0 => {
@@ -207,21 +168,39 @@ impl Game {
                 let pai_num: u8 = t[1..].to_string().parse().unwrap();
                 let pai = Pai::from(pai_num);
                 let tsumogiri = Some(pai) == game.last_draw;
+                if let Some(MajEvent::Reach { actor: reach_actor, step: ReachStep::Riichi, pai: declared, tsumogiri: declared_tsumogiri, .. }) = game.game.last_mut() {
+                    if *reach_actor == actor {
+                        *declared = Some(pai);
+                        *declared_tsumogiri = Some(tsumogiri);
+                    }
+                }
                 game.game.push(MajEvent::Dahai {
                     junme: game.junme[actor as usize],
                     actor,
                     pai,
-                    r#type: "dahai",
                     tsumogiri,
                 });
                 game.last_draw = None;
             }
             "RYUUKYOKU" => {
-                let reason = e.get_attribute("type").unwrap_or("howanpai".to_string());
+                let reason = e.get_attribute("type").map(|t| t.parse().unwrap()).unwrap_or_default();
+                let tehais: [Option<Vec<Pai>>; 4] = [
+                    e.get_attribute("hai0").map(|x| x.into_pai_vec()),
+                    e.get_attribute("hai1").map(|x| x.into_pai_vec()),
+                    e.get_attribute("hai2").map(|x| x.into_pai_vec()),
+                    e.get_attribute("hai3").map(|x| x.into_pai_vec()),
+                ];
+                let tenpai_shown = matches!(reason, RyuukyokuReason::Howanpai | RyuukyokuReason::Nm);
+                let tenpai = tehais.each_ref().map(|x| tenpai_shown && x.is_some());
+                let deltas = Self::parse_deltas(e);
+                let nagashi = deltas.map(|x| reason == RyuukyokuReason::Nm && x > 0);
                 let game = self.games.last_mut().unwrap();
                 game.game.push(MajEvent::Ryuukyoku {
                     reason,
-                    r#type: "ryuukyoku",
+                    tehais,
+                    tenpai,
+                    deltas,
+                    nagashi,
                 });
                 self.update_owari(e);
             }
@@ -229,20 +208,23 @@ impl Game {
                 let pai_num: u8 = e.get_attribute("hai").unwrap().parse().unwrap();
                 let pai = Pai::from(pai_num);
                 let game = self.games.last_mut().unwrap();
-                game.game.push(MajEvent::Dora {
-                    dora_marker: pai,
-                    r#type: "dora",
-                });
+                game.game.push(MajEvent::Dora { dora_marker: pai });
             }
             "REACH" => {
                 let actor: u8 = e.get_attribute("who").unwrap().parse().unwrap();
                 let typenum: u8 = e.get_attribute("step").unwrap().parse().unwrap();
-                let r#type = if typenum == 1 { "riichi" } else { "riichi_accepted" };
+                let step = if typenum == 1 { ReachStep::Riichi } else { ReachStep::RiichiAccepted };
+                let scores = e.get_attribute("ten").map(|x| {
+                    x.into_num_vec().iter().map(|&x: &i32| x * 100).collect::<Vec<i32>>().try_into().unwrap()
+                });
                 let game = self.games.last_mut().unwrap();
                 game.game.push(MajEvent::Reach {
                     junme: game.junme[actor as usize],
                     actor,
-                    r#type,
+                    step,
+                    pai: None,
+                    tsumogiri: None,
+                    scores,
                 });
             }
             "AGARI" => {
@@ -255,11 +237,11 @@ impl Game {
                 let yaku = if let Some(yaku) = e.get_attribute("yaku") {
                     yaku.into_num_vec().chunks(2).flat_map(|y| {
                         let [nowyaku, val] = [y[0], y[1]];
-                        return if nowyaku == 52 || nowyaku == 53 || nowyaku == 54 {
+                        if nowyaku == 52 || nowyaku == 53 || nowyaku == 54 {
                             (0..val).map(|_| (nowyaku, 1)).collect::<Vec<_>>()
                         } else {
                             vec![(nowyaku, val)]
-                        };
+                        }
                     }).collect()
                 } else {
                     e.get_attribute("yakuman").unwrap().into_num_vec().iter().map(|&x: &u8| (x, 13)).collect::<Vec<_>>()
@@ -274,15 +256,14 @@ impl Game {
                 let junme = game.junme[actor as usize];
                 let paowho = e.get_attribute("paoWho").map(|x| x.parse().unwrap());
                 let fromwho = e.get_attribute("fromWho").unwrap().parse().unwrap();
-                let naki = if let Some(naki_raw_list) = e.get_attribute("m") {
-                    Some(naki_raw_list.into_num_vec().iter().map(|&naki_raw: &i32| {
-                        Self::parse_naki(actor, naki_raw as u32, None)
-                    }).collect())
-                } else {
-                    None
-                };
+                let naki = e.get_attribute("m").map(|naki_raw_list| {
+                    naki_raw_list.into_num_vec().iter().map(|&naki_raw: &i32| {
+                        decode_naki(actor, naki_raw as u32, None)
+                    }).collect()
+                });
                 let dora_marker = e.get_attribute("doraHai").unwrap().into_num_vec().iter().map(|&x: &u8| Pai::from(x)).collect();
                 let ura_marker = e.get_attribute("doraHaiUra").map(|x| x.into_num_vec().iter().map(|&x: &u8| Pai::from(x)).collect());
+                let deltas = Self::parse_deltas(e);
                 game.game.push(MajEvent::Agari {
                     honba,
                     kyotaku,
@@ -299,7 +280,7 @@ impl Game {
                     actor,
                     fromwho,
                     paowho,
-                    r#type: "agari",
+                    deltas,
                 });
                 self.update_owari(e);
             }
@@ -308,7 +289,13 @@ impl Game {
                 let m = e.get_attribute("m").unwrap().parse().unwrap();
                 let game = self.games.last_mut().unwrap();
                 game.junme[actor as usize] += 1;
-                game.game.push(Self::parse_naki(actor, m, Some(game.junme[actor as usize])));
+                game.game.push(decode_naki(actor, m, Some(game.junme[actor as usize])));
+            }
+            "BYE" => {
+                let actor: u8 = e.get_attribute("who").unwrap().parse().unwrap();
+                if let Some(game) = self.games.last_mut() {
+                    game.game.push(MajEvent::Disconnect { actor });
+                }
             }
             _ => {
                 warn!("Unknown tag: {:?}", e.name());
@@ -316,7 +303,7 @@ impl Game {
         }
     }
 
-    fn parse_reader<R: BufRead>(mut xml_reader: Reader<R>) -> Self {
+    pub(crate) fn parse_reader<R: BufRead>(mut xml_reader: Reader<R>, options: ParseOptions) -> Self {
         let mut buf = Vec::new();
         let mut game = Game::default();
 
@@ -342,16 +329,45 @@ impl Game {
                 _ => {}
             }
         }
+        for (i, round) in game.games.iter().enumerate() {
+            if round.horas().iter().any(|x| x.is_multi_ron() && !x.sticks_to_first_winner()) {
+                warn!("Honba or riichi sticks not paid to the first winner in round {}", i);
+            }
+        }
+        game.raw_names = options.raw_names;
+        if !options.raw_names {
+            game.id = game.id.each_ref().map(|x| decode_name(x));
+        }
         game
     }
 
+    /// Rank of the named player at the time of the game, as recorded in the UN tag.
+    /// Anonymous players can't be told apart and have no rank.
+    pub fn rank(&self, name: &str) -> Option<Dan> {
+        if name == ANONYMOUS {
+            return None;
+        }
+        self.id.iter().position(|x| x == name).map(|seat| self.dan[seat])
+    }
+
     pub fn parse_xml_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::parse_xml_file_with(path, ParseOptions::default())
+    }
+
+    /// Also fills in `meta` if the file name contains a log id.
+    pub fn parse_xml_file_with<P: AsRef<Path>>(path: P, options: ParseOptions) -> Self {
+        let path = path.as_ref();
         let reader = Reader::from_file(path).unwrap();
-        Self::parse_reader(reader)
+        let mut game = Self::parse_reader(reader, options);
+        game.meta = path.file_name().and_then(|x| GameMeta::parse(&x.to_string_lossy()));
+        game
+    }
+
+    pub fn parse_json_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::read_from_file(path, Encoding::Json)
     }
 
     pub fn write_to_json<P: AsRef<Path>>(&self, path: P) {
-        let json = serde_json::to_string_pretty(self).unwrap();
-        std::fs::write(path, json).unwrap();
+        self.write_to_file(path, Encoding::PrettyJson);
     }
 }
\ No newline at end of file
@@ -1,21 +1,81 @@
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error;
 use serde::ser::SerializeStruct;
-use crate::enums::{MajEvent, Pai, PaiColor};
+use crate::enums::{Dan, MajEvent, NakiKind, Pai, PaiColor, ReachStep, RyuukyokuReason};
+use crate::outcome::Hora;
+use crate::round::{Round, RoundData};
 
 impl Serialize for Pai {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let num = if self.num == 5 && self.idx == 0 && self.color != PaiColor::Jihai { 0 } else { self.num };
-        let color = match self.color {
-            PaiColor::Manzu => 'm',
-            PaiColor::Pinzu => 'p',
-            PaiColor::Souzu => 's',
-            PaiColor::Jihai => 'z',
-            _ => panic!("Invalid color for Pai"),
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The tile id is not part of the notation, so every tile comes back as the first copy
+/// except the black five, which comes back as the second copy to keep it apart from the red one.
+/// Rounds restore the ids from their `copies`.
+impl<'de> Deserialize<'de> for Pai {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let mut chars = s.chars();
+        let (Some(num), Some(color), None) = (chars.next().and_then(|c| c.to_digit(10)), chars.next(), chars.next()) else {
+            return Err(D::Error::custom(format!("Invalid Pai: {}", s)));
         };
-        serializer.serialize_str(&format!("{}{}", num, color))
+        let color = match color {
+            'm' => PaiColor::Manzu,
+            'p' => PaiColor::Pinzu,
+            's' => PaiColor::Souzu,
+            'z' => PaiColor::Jihai,
+            _ => return Err(D::Error::custom(format!("Invalid Pai: {}", s))),
+        };
+        let (num, idx) = match (num as u8, color) {
+            (0, PaiColor::Jihai) | (8..=9, PaiColor::Jihai) => return Err(D::Error::custom(format!("Invalid Pai: {}", s))),
+            (0, _) => (5, 0),
+            (5, PaiColor::Jihai) => (5, 0),
+            (5, _) => (5, 1),
+            (num, _) => (num, 0),
+        };
+        Ok(Pai { num, color, idx })
+    }
+}
+
+
+impl Serialize for Dan {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Dan", 2)?;
+        state.serialize_field("index", &u8::from(*self))?;
+        state.serialize_field("name", self.name())?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DanRepr {
+    Index(u8),
+    Named { index: u8 },
+}
+
+/// Also reads the bare index written before names were added.
+impl<'de> Deserialize<'de> for Dan {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (DanRepr::Index(index) | DanRepr::Named { index }) = DanRepr::deserialize(deserializer)?;
+        if index > u8::from(Dan::Tenhou) {
+            return Err(D::Error::custom(format!("Invalid dan: {}", index)));
+        }
+        Ok(Dan::from(index))
     }
 }
 
@@ -30,7 +90,7 @@ impl Serialize for MajEvent {
                 serializer.serialize_str("Unknown")
             }
             MajEvent::Init { bakaze, dora_marker, honba, kyoku, kyotaku, oya, scores, tehais } => {
-                let mut state = serializer.serialize_struct("Init", 7)?;
+                let mut state = serializer.serialize_struct("Init", 9)?;
                 state.serialize_field("bakaze", bakaze)?;
                 state.serialize_field("dora_marker", dora_marker)?;
                 state.serialize_field("honba", honba)?;
@@ -39,47 +99,60 @@ impl Serialize for MajEvent {
                 state.serialize_field("oya", oya)?;
                 state.serialize_field("scores", scores)?;
                 state.serialize_field("tehais", tehais)?;
+                state.serialize_field("type", "init")?;
                 state.end()
             }
-            MajEvent::Ryuukyoku { reason, r#type } => {
-                let mut state = serializer.serialize_struct("Ryuukyoku", 2)?;
+            MajEvent::Ryuukyoku { reason, tehais, tenpai, deltas, nagashi } => {
+                let mut state = serializer.serialize_struct("Ryuukyoku", 6)?;
                 state.serialize_field("reason", reason)?;
-                state.serialize_field("type", r#type)?;
+                state.serialize_field("tehais", tehais)?;
+                state.serialize_field("tenpai", tenpai)?;
+                state.serialize_field("deltas", deltas)?;
+                state.serialize_field("nagashi", nagashi)?;
+                state.serialize_field("type", "ryuukyoku")?;
                 state.end()
             }
-            MajEvent::Dora { dora_marker, r#type } => {
+            MajEvent::Dora { dora_marker } => {
                 let mut state = serializer.serialize_struct("Dora", 2)?;
                 state.serialize_field("dora_marker", dora_marker)?;
-                state.serialize_field("type", r#type)?;
+                state.serialize_field("type", "dora")?;
                 state.end()
             }
 
-            MajEvent::Reach { junme, actor, r#type } => {
-                let mut state = serializer.serialize_struct("Reach", 3)?;
+            MajEvent::Reach { junme, actor, step, pai, tsumogiri, scores } => {
+                let len = 3 + if pai.is_some() { 2 } else { 0 } + if scores.is_some() { 1 } else { 0 };
+                let mut state = serializer.serialize_struct("Reach", len)?;
                 state.serialize_field("junme", junme)?;
                 state.serialize_field("actor", actor)?;
-                state.serialize_field("type", r#type)?;
+                if pai.is_some() {
+                    state.serialize_field("pai", pai)?;
+                    state.serialize_field("tsumogiri", tsumogiri)?;
+                }
+                if scores.is_some() {
+                    state.serialize_field("scores", scores)?;
+                }
+                state.serialize_field("type", step)?;
                 state.end()
             }
-            MajEvent::Dahai { junme, actor, pai, r#type, tsumogiri } => {
-                let mut state = serializer.serialize_struct("Dahai", 4)?;
+            MajEvent::Dahai { junme, actor, pai, tsumogiri } => {
+                let mut state = serializer.serialize_struct("Dahai", 5)?;
                 state.serialize_field("junme", junme)?;
                 state.serialize_field("actor", actor)?;
                 state.serialize_field("pai", pai)?;
-                state.serialize_field("type", r#type)?;
+                state.serialize_field("type", "dahai")?;
                 state.serialize_field("tsumogiri", tsumogiri)?;
                 state.end()
             }
-            MajEvent::Tsumo { junme, actor, pai, r#type } => {
+            MajEvent::Tsumo { junme, actor, pai } => {
                 let mut state = serializer.serialize_struct("Tsumo", 4)?;
                 state.serialize_field("junme", junme)?;
                 state.serialize_field("actor", actor)?;
                 state.serialize_field("pai", pai)?;
-                state.serialize_field("type", r#type)?;
+                state.serialize_field("type", "tsumo")?;
                 state.end()
             }
-            MajEvent::Naki { junme, actor, consumed, pai, target, r#type } => {
-                let mut state = serializer.serialize_struct("Naki", 5)?;
+            MajEvent::Naki { junme, actor, consumed, pai, target, kind } => {
+                let mut state = serializer.serialize_struct("Naki", if junme.is_some() { 6 } else { 4 })?;
                 if junme.is_some() {
                     state.serialize_field("junme", junme)?;
                     state.serialize_field("actor", actor)?;
@@ -88,11 +161,11 @@ impl Serialize for MajEvent {
                 state.serialize_field("consumed", consumed)?;
                 state.serialize_field("pai", pai)?;
                 state.serialize_field("target", target)?;
-                state.serialize_field("type", r#type)?;
+                state.serialize_field("type", kind)?;
                 state.end()
             }
-            MajEvent::Agari { honba, kyotaku, junme, hai, naki, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, r#type } => {
-                let mut state = serializer.serialize_struct("Agari", 14)?;
+            MajEvent::Agari { honba, kyotaku, junme, hai, naki, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas } => {
+                let mut state = serializer.serialize_struct("Agari", 17)?;
                 state.serialize_field("honba", honba)?;
                 state.serialize_field("kyotaku", kyotaku)?;
                 state.serialize_field("junme", junme)?;
@@ -108,10 +181,586 @@ impl Serialize for MajEvent {
                 state.serialize_field("actor", actor)?;
                 state.serialize_field("fromwho", fromwho)?;
                 state.serialize_field("paowho", paowho)?;
-                state.serialize_field("type", r#type)?;
+                state.serialize_field("deltas", deltas)?;
+                state.serialize_field("type", "agari")?;
+                state.end()
+            }
+            MajEvent::Disconnect { actor } => {
+                let mut state = serializer.serialize_struct("Disconnect", 2)?;
+                state.serialize_field("actor", actor)?;
+                state.serialize_field("type", "disconnect")?;
                 state.end()
             }
+            MajEvent::Reconnect { actor } => {
+                let mut state = serializer.serialize_struct("Reconnect", 2)?;
+                state.serialize_field("actor", actor)?;
+                state.serialize_field("type", "reconnect")?;
+                state.end()
+            }
+        }
+    }
+}
+
+
+
+/// Mirror of the serialized layout of `MajEvent`, tagged by the `type` field.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TaggedEvent {
+    Init(InitFields),
+    /// Only `reason` was written before the hands and score changes of draws were kept.
+    Ryuukyoku {
+        reason: RyuukyokuReason,
+        #[serde(default)]
+        tehais: [Option<Vec<Pai>>; 4],
+        #[serde(default)]
+        tenpai: [bool; 4],
+        #[serde(default)]
+        deltas: [i32; 4],
+        #[serde(default)]
+        nagashi: [bool; 4],
+    },
+    Dora {
+        dora_marker: Pai,
+    },
+    Riichi {
+        junme: u8,
+        actor: u8,
+        #[serde(default)]
+        pai: Option<Pai>,
+        #[serde(default)]
+        tsumogiri: Option<bool>,
+    },
+    RiichiAccepted {
+        junme: u8,
+        actor: u8,
+        #[serde(default)]
+        scores: Option<[i32; 4]>,
+    },
+    Dahai {
+        junme: u8,
+        actor: u8,
+        pai: Pai,
+        tsumogiri: bool,
+    },
+    Tsumo {
+        junme: u8,
+        actor: u8,
+        pai: Pai,
+    },
+    Chii(TaggedNaki),
+    Pon(TaggedNaki),
+    Kakan(TaggedNaki),
+    Ankan(TaggedNaki),
+    Daiminkan(TaggedNaki),
+    Nukidora(TaggedNaki),
+    Agari {
+        honba: u8,
+        kyotaku: u8,
+        junme: u8,
+        hai: Vec<Pai>,
+        naki: Option<Vec<MajEvent>>,
+        machi: Pai,
+        han: u8,
+        hu: u8,
+        score: i32,
+        yaku: Vec<u8>,
+        dora_marker: Vec<Pai>,
+        ura_marker: Option<Vec<Pai>>,
+        actor: u8,
+        fromwho: u8,
+        paowho: Option<u8>,
+        #[serde(default)]
+        deltas: [i32; 4],
+    },
+    Disconnect {
+        actor: u8,
+    },
+    Reconnect {
+        actor: u8,
+    },
+}
+
+#[derive(Deserialize)]
+struct InitFields {
+    bakaze: String,
+    dora_marker: Pai,
+    honba: u8,
+    kyoku: u8,
+    kyotaku: u8,
+    oya: u8,
+    scores: [i32; 4],
+    tehais: [[Pai; 13]; 4],
+}
+
+/// Melds inside `Agari` are written without `junme` and `actor`.
+#[derive(Deserialize)]
+struct TaggedNaki {
+    #[serde(default)]
+    junme: Option<u8>,
+    #[serde(default)]
+    actor: u8,
+    consumed: Vec<Pai>,
+    pai: Option<Pai>,
+    target: Option<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EventRepr {
+    Unknown(String),
+    Tagged(TaggedEvent),
+    /// `Init` was written without `type` before every event had one.
+    Init(InitFields),
+}
+
+impl TaggedNaki {
+    fn into_event(self, kind: NakiKind) -> MajEvent {
+        MajEvent::Naki {
+            junme: self.junme,
+            actor: self.actor,
+            consumed: self.consumed,
+            pai: self.pai,
+            target: self.target,
+            kind,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MajEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let event = match EventRepr::deserialize(deserializer)? {
+            EventRepr::Unknown(s) if s == "Unknown" => return Ok(MajEvent::Unknown),
+            EventRepr::Unknown(s) => return Err(D::Error::custom(format!("Invalid event: {}", s))),
+            EventRepr::Tagged(event) => event,
+            EventRepr::Init(init) => TaggedEvent::Init(init),
+        };
+        Ok(match event {
+            TaggedEvent::Init(InitFields { bakaze, dora_marker, honba, kyoku, kyotaku, oya, scores, tehais }) => {
+                let bakaze = match bakaze.as_str() {
+                    "E" => "E",
+                    "S" => "S",
+                    "W" => "W",
+                    "N" => "N",
+                    _ => return Err(D::Error::custom(format!("Invalid bakaze: {}", bakaze))),
+                };
+                MajEvent::Init { bakaze, dora_marker, honba, kyoku, kyotaku, oya, scores, tehais }
+            }
+            TaggedEvent::Ryuukyoku { reason, tehais, tenpai, deltas, nagashi } => {
+                MajEvent::Ryuukyoku { reason, tehais, tenpai, deltas, nagashi }
+            }
+            TaggedEvent::Dora { dora_marker } => MajEvent::Dora { dora_marker },
+            TaggedEvent::Riichi { junme, actor, pai, tsumogiri } => {
+                MajEvent::Reach { junme, actor, step: ReachStep::Riichi, pai, tsumogiri, scores: None }
+            }
+            TaggedEvent::RiichiAccepted { junme, actor, scores } => {
+                MajEvent::Reach { junme, actor, step: ReachStep::RiichiAccepted, pai: None, tsumogiri: None, scores }
+            }
+            TaggedEvent::Dahai { junme, actor, pai, tsumogiri } => MajEvent::Dahai { junme, actor, pai, tsumogiri },
+            TaggedEvent::Tsumo { junme, actor, pai } => MajEvent::Tsumo { junme, actor, pai },
+            TaggedEvent::Chii(naki) => naki.into_event(NakiKind::Chii),
+            TaggedEvent::Pon(naki) => naki.into_event(NakiKind::Pon),
+            TaggedEvent::Kakan(naki) => naki.into_event(NakiKind::Kakan),
+            TaggedEvent::Ankan(naki) => naki.into_event(NakiKind::Ankan),
+            TaggedEvent::Daiminkan(naki) => naki.into_event(NakiKind::Daiminkan),
+            TaggedEvent::Nukidora(naki) => naki.into_event(NakiKind::Nukidora),
+            TaggedEvent::Agari { honba, kyotaku, junme, hai, mut naki, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas } => {
+                for event in naki.iter_mut().flatten() {
+                    if let MajEvent::Naki { actor: naki_actor, .. } = event {
+                        *naki_actor = actor;
+                    }
+                }
+                MajEvent::Agari { honba, kyotaku, junme, hai, naki, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas }
+            }
+            TaggedEvent::Disconnect { actor } => MajEvent::Disconnect { actor },
+            TaggedEvent::Reconnect { actor } => MajEvent::Reconnect { actor },
+        })
+    }
+}
+
+/// Every tile of an event in the order they are written, melds of `Agari` included.
+fn event_pais<'a>(event: &'a MajEvent, pais: &mut Vec<&'a Pai>) {
+    match event {
+        MajEvent::Init { dora_marker, tehais, .. } => {
+            pais.push(dora_marker);
+            pais.extend(tehais.iter().flatten());
+        }
+        MajEvent::Ryuukyoku { tehais, .. } => pais.extend(tehais.iter().flatten().flatten()),
+        MajEvent::Dora { dora_marker } => pais.push(dora_marker),
+        MajEvent::Reach { pai, .. } => pais.extend(pai),
+        MajEvent::Dahai { pai, .. } | MajEvent::Tsumo { pai, .. } => pais.push(pai),
+        MajEvent::Naki { consumed, pai, .. } => {
+            pais.extend(consumed);
+            pais.extend(pai);
+        }
+        MajEvent::Agari { hai, naki, machi, dora_marker, ura_marker, .. } => {
+            pais.extend(hai);
+            for meld in naki.iter().flatten() {
+                event_pais(meld, pais);
+            }
+            pais.push(machi);
+            pais.extend(dora_marker);
+            pais.extend(ura_marker.iter().flatten());
+        }
+        MajEvent::Unknown | MajEvent::Disconnect { .. } | MajEvent::Reconnect { .. } => {}
+    }
+}
+
+fn event_pais_mut<'a>(event: &'a mut MajEvent, pais: &mut Vec<&'a mut Pai>) {
+    match event {
+        MajEvent::Init { dora_marker, tehais, .. } => {
+            pais.push(dora_marker);
+            pais.extend(tehais.iter_mut().flatten());
+        }
+        MajEvent::Ryuukyoku { tehais, .. } => pais.extend(tehais.iter_mut().flatten().flatten()),
+        MajEvent::Dora { dora_marker } => pais.push(dora_marker),
+        MajEvent::Reach { pai, .. } => pais.extend(pai),
+        MajEvent::Dahai { pai, .. } | MajEvent::Tsumo { pai, .. } => pais.push(pai),
+        MajEvent::Naki { consumed, pai, .. } => {
+            pais.extend(consumed);
+            pais.extend(pai);
+        }
+        MajEvent::Agari { hai, naki, machi, dora_marker, ura_marker, .. } => {
+            pais.extend(hai);
+            for meld in naki.iter_mut().flatten() {
+                event_pais_mut(meld, pais);
+            }
+            pais.push(machi);
+            pais.extend(dora_marker);
+            pais.extend(ura_marker.iter_mut().flatten());
+        }
+        MajEvent::Unknown | MajEvent::Disconnect { .. } | MajEvent::Reconnect { .. } => {}
+    }
+}
+
+/// `horas` is derived from `game` and written for readers of the output only, it is ignored when read back.
+/// `copies` keeps the tile ids the notation leaves out, one digit per tile in the order of `data` then `game`.
+impl Serialize for Round {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut pais = vec![&self.data.dora_marker];
+        pais.extend(self.data.tehais.iter().flatten());
+        for event in &self.game {
+            event_pais(event, &mut pais);
+        }
+        let copies: String = pais.iter().map(|pai| char::from(b'0' + pai.idx)).collect();
+
+        let mut state = serializer.serialize_struct("Round", 4)?;
+        state.serialize_field("data", &self.data)?;
+        state.serialize_field("game", &self.game)?;
+        state.serialize_field("horas", &self.horas())?;
+        state.serialize_field("copies", &copies)?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct RoundRepr {
+    data: RoundData,
+    game: Vec<MajEvent>,
+    /// Missing from output written before the tile ids were kept.
+    #[serde(default)]
+    copies: Option<String>,
+}
+
+/// Without `copies` the tiles keep the ids given by the `Pai` notation.
+impl<'de> Deserialize<'de> for Round {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let RoundRepr { mut data, mut game, copies } = RoundRepr::deserialize(deserializer)?;
+        if let Some(copies) = copies {
+            let mut pais = vec![&mut data.dora_marker];
+            pais.extend(data.tehais.iter_mut().flatten());
+            for event in game.iter_mut() {
+                event_pais_mut(event, &mut pais);
+            }
+            if pais.len() != copies.len() {
+                return Err(D::Error::custom(format!("{} copies for {} tiles", copies.len(), pais.len())));
+            }
+            for (pai, copy) in pais.into_iter().zip(copies.chars()) {
+                let notation = pai.to_string();
+                match copy.to_digit(10) {
+                    Some(idx @ 0..=3) => pai.idx = idx as u8,
+                    _ => return Err(D::Error::custom(format!("Invalid copy: {}", copy))),
+                }
+                if pai.to_string() != notation {
+                    return Err(D::Error::custom(format!("Copy {} does not match {}", copy, notation)));
+                }
+            }
         }
+        Ok(Round { data, game, ..Default::default() })
     }
 }
 
+impl Serialize for Hora<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Hora", 4)?;
+        state.serialize_field("fromwho", &self.fromwho)?;
+        state.serialize_field("pai", &self.pai)?;
+        state.serialize_field("winners", &self.winners())?;
+        state.serialize_field("seq", &self.seq)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::Encoding;
+    use crate::enums::{MajEvent, NakiKind, Pai, ReachStep, RyuukyokuReason};
+    use crate::round::Game;
+    use crate::testing::fixture;
+
+    fn to_json<T: serde::Serialize>(value: &T) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    fn pai(s: &str) -> Result<Pai, serde_json::Error> {
+        serde_json::from_str(&format!("\"{}\"", s))
+    }
+
+    #[test]
+    fn game_round_trips_through_every_encoding() {
+        let game = fixture();
+        let json = to_json(&game);
+        for encoding in [Encoding::PrettyJson, Encoding::Json, Encoding::MessagePack, Encoding::Cbor] {
+            let mut bytes = Vec::new();
+            game.write_to(&mut bytes, encoding);
+            let read = Game::read_from(bytes.as_slice(), encoding);
+            assert_eq!(to_json(&read), json, "{:?}", encoding);
+
+            let mut again = Vec::new();
+            read.write_to(&mut again, encoding);
+            assert_eq!(again, bytes, "{:?}", encoding);
+        }
+    }
+
+    fn tile_ids(game: &Game) -> Vec<Vec<u8>> {
+        game.games.iter().map(|round| {
+            let mut pais = vec![&round.data.dora_marker];
+            pais.extend(round.data.tehais.iter().flatten());
+            for event in &round.game {
+                super::event_pais(event, &mut pais);
+            }
+            pais.into_iter().map(|pai| u8::from(*pai)).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn tile_ids_survive_every_encoding() {
+        let game = fixture();
+        let mut xml = Vec::new();
+        game.write_xml(&mut xml);
+        for encoding in [Encoding::PrettyJson, Encoding::Json, Encoding::MessagePack, Encoding::Cbor] {
+            let mut bytes = Vec::new();
+            game.write_to(&mut bytes, encoding);
+            let read = Game::read_from(bytes.as_slice(), encoding);
+            assert_eq!(tile_ids(&read), tile_ids(&game), "{:?}", encoding);
+
+            let mut again = Vec::new();
+            read.write_xml(&mut again);
+            assert_eq!(String::from_utf8(again).unwrap(), String::from_utf8(xml.clone()).unwrap(), "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn rounds_without_copies_keep_the_notation() {
+        let game = fixture();
+        let without_copies = |game: &Game| {
+            let mut value = serde_json::to_value(game).unwrap();
+            for round in value["games"].as_array_mut().unwrap() {
+                round.as_object_mut().unwrap().remove("copies");
+            }
+            value
+        };
+        let read: Game = serde_json::from_value(without_copies(&game)).unwrap();
+        assert_eq!(without_copies(&read), without_copies(&game));
+        assert_ne!(tile_ids(&read), tile_ids(&game));
+    }
+
+    #[test]
+    fn copies_must_match_the_tiles() {
+        let game = fixture();
+        let value = serde_json::to_value(&game).unwrap();
+        let copies = value["games"][0]["copies"].as_str().unwrap().to_string();
+
+        let mut short = value.clone();
+        short["games"][0]["copies"] = serde_json::Value::from(&copies[1..]);
+        assert!(serde_json::from_value::<Game>(short).is_err());
+
+        let mut invalid = value.clone();
+        invalid["games"][0]["copies"] = serde_json::Value::from(format!("4{}", &copies[1..]));
+        assert!(serde_json::from_value::<Game>(invalid).is_err());
+
+        // The dora marker of the fixture is not a five, so a red five is the first tile dealt to find.
+        let red = tile_ids(&game)[0].iter().position(|&id| [16, 52, 88].contains(&id)).unwrap();
+        let mut black = value;
+        black["games"][0]["copies"] = serde_json::Value::from(format!("{}1{}", &copies[..red], &copies[red + 1..]));
+        assert!(serde_json::from_value::<Game>(black).is_err());
+    }
+
+    #[test]
+    fn output_written_before_deltas_and_draw_hands_loads() {
+        let game = fixture();
+        let mut value = serde_json::to_value(&game).unwrap();
+        for round in value["games"].as_array_mut().unwrap() {
+            let round = round.as_object_mut().unwrap();
+            round.remove("horas");
+            round.remove("copies");
+            for event in round["game"].as_array_mut().unwrap() {
+                let Some(event) = event.as_object_mut() else { continue };
+                let fields: &[&str] = match event["type"].as_str().unwrap() {
+                    "agari" => &["deltas"],
+                    "ryuukyoku" => &["tehais", "tenpai", "deltas", "nagashi"],
+                    _ => &[],
+                };
+                for field in fields {
+                    assert!(event.remove(*field).is_some(), "{}", field);
+                }
+            }
+        }
+        let read: Game = serde_json::from_value(value).unwrap();
+        assert_eq!(read.games.len(), game.games.len());
+        for (read, round) in read.games.iter().zip(&game.games) {
+            assert_eq!(read.game.len(), round.game.len());
+            for event in &read.game {
+                match event {
+                    MajEvent::Agari { deltas, .. } => assert_eq!(*deltas, [0; 4]),
+                    MajEvent::Ryuukyoku { tehais, tenpai, deltas, nagashi, .. } => {
+                        assert_eq!(*tehais, [None, None, None, None]);
+                        assert_eq!((*tenpai, *deltas, *nagashi), ([false; 4], [0; 4], [false; 4]));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn init_without_type_loads() {
+        let init = MajEvent::Init {
+            bakaze: "E",
+            dora_marker: Pai::from(35),
+            honba: 0,
+            kyoku: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [25000; 4],
+            tehais: [0, 1, 2, 3].map(|seat| std::array::from_fn(|i| Pai::from(seat * 13 + i as u8))),
+        };
+        let mut value = serde_json::to_value(&init).unwrap();
+        assert_eq!(value.as_object_mut().unwrap().remove("type").unwrap(), "init");
+        let read: MajEvent = serde_json::from_value(value).unwrap();
+        assert_eq!(to_json(&read), to_json(&init));
+    }
+
+    #[test]
+    fn every_event_round_trips() {
+        let p = Pai::from;
+        let naki = |junme, kind, consumed: Vec<u8>, pai: Option<u8>, target| MajEvent::Naki {
+            junme,
+            actor: 2,
+            consumed: consumed.into_iter().map(p).collect(),
+            pai: pai.map(p),
+            target,
+            kind,
+        };
+        let events = vec![
+            MajEvent::Unknown,
+            MajEvent::Init {
+                bakaze: "S",
+                dora_marker: p(16),
+                honba: 1,
+                kyoku: 3,
+                kyotaku: 2,
+                oya: 2,
+                scores: [25000, 24000, 26000, 23000],
+                tehais: [0, 1, 2, 3].map(|seat| std::array::from_fn(|i| p(seat * 13 + i as u8))),
+            },
+            MajEvent::Ryuukyoku {
+                reason: RyuukyokuReason::Howanpai,
+                tehais: [Some(vec![p(0), p(4), p(8)]), None, None, Some(vec![p(17)])],
+                tenpai: [true, false, false, true],
+                deltas: [1500, -1500, -1500, 1500],
+                nagashi: [false; 4],
+            },
+            MajEvent::Ryuukyoku { reason: RyuukyokuReason::Yao9, tehais: [None, Some(vec![p(0)]), None, None], tenpai: [false; 4], deltas: [0; 4], nagashi: [false; 4] },
+            MajEvent::Dora { dora_marker: p(135) },
+            MajEvent::Reach { junme: 5, actor: 1, step: ReachStep::Riichi, pai: None, tsumogiri: None, scores: None },
+            MajEvent::Reach { junme: 5, actor: 1, step: ReachStep::Riichi, pai: Some(p(52)), tsumogiri: Some(true), scores: None },
+            MajEvent::Reach { junme: 5, actor: 1, step: ReachStep::RiichiAccepted, pai: None, tsumogiri: None, scores: None },
+            MajEvent::Reach { junme: 5, actor: 1, step: ReachStep::RiichiAccepted, pai: None, tsumogiri: None, scores: Some([25000, 24000, 25000, 25000]) },
+            MajEvent::Dahai { junme: 3, actor: 0, pai: p(88), tsumogiri: false },
+            MajEvent::Tsumo { junme: 3, actor: 0, pai: p(52) },
+            naki(Some(4), NakiKind::Chii, vec![4, 8], Some(0), Some(1)),
+            naki(Some(4), NakiKind::Pon, vec![108, 109], Some(110), Some(0)),
+            naki(Some(4), NakiKind::Kakan, vec![108, 109, 110], Some(111), Some(0)),
+            naki(Some(4), NakiKind::Ankan, vec![120, 121, 122, 123], None, None),
+            naki(Some(4), NakiKind::Daiminkan, vec![16, 17, 18], Some(19), Some(3)),
+            naki(Some(4), NakiKind::Nukidora, vec![121], None, None),
+            MajEvent::Agari {
+                honba: 1,
+                kyotaku: 1,
+                junme: 9,
+                hai: vec![p(0), p(1), p(2), p(16), p(17)],
+                naki: Some(vec![naki(None, NakiKind::Pon, vec![108, 109], Some(110), Some(0))]),
+                machi: p(17),
+                han: 3,
+                hu: 30,
+                score: 3900,
+                yaku: vec![8, 52, 52],
+                dora_marker: vec![p(12)],
+                ura_marker: None,
+                actor: 2,
+                fromwho: 0,
+                paowho: Some(0),
+                deltas: [-4200, 0, 5200, 0],
+            },
+            MajEvent::Disconnect { actor: 3 },
+            MajEvent::Reconnect { actor: 3 },
+        ];
+        for event in &events {
+            let json = to_json(event);
+            let read: MajEvent = serde_json::from_str(&json).unwrap();
+            assert_eq!(to_json(&read), json);
+            assert_eq!(read.type_name(), event.type_name());
+            assert_eq!(read.actor(), event.actor());
+        }
+    }
+
+    #[test]
+    fn red_and_black_fives() {
+        assert_eq!(Pai::from(16).to_string(), "0m");
+        assert_eq!(Pai::from(17).to_string(), "5m");
+        assert_eq!(Pai::from(19).to_string(), "5m");
+        assert_eq!(Pai::from(124).to_string(), "5z");
+
+        // Only the red five keeps its tile id, the other copies come back as the second one.
+        assert_eq!(u8::from(pai("0m").unwrap()), 16);
+        assert_eq!(u8::from(pai("5m").unwrap()), 17);
+        assert_eq!(u8::from(pai("0p").unwrap()), 52);
+        assert_eq!(u8::from(pai("5s").unwrap()), 89);
+        assert_eq!(u8::from(pai("5z").unwrap()), 124);
+        assert_eq!(u8::from(pai("1z").unwrap()), 108);
+        for id in [16, 17, 52, 53, 88, 89, 124] {
+            let read = pai(&Pai::from(id).to_string()).unwrap();
+            assert_eq!(read.to_string(), Pai::from(id).to_string());
+        }
+    }
+
+    #[test]
+    fn invalid_tiles_are_rejected() {
+        for s in ["8z", "9z", "0z", "5x", "m5", "10m", "5", "", "55m"] {
+            assert!(pai(s).is_err(), "{}", s);
+        }
+    }
+}
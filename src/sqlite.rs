@@ -0,0 +1,290 @@
+use std::path::Path;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::enums::{MajEvent, Pai};
+use crate::round::Game;
+
+/// Games inserted per transaction.
+const GAMES_PER_TRANSACTION: usize = 1000;
+
+/// Stored in `PRAGMA user_version`. Bump it and add to `ADDED_COLUMNS` when a column is added.
+const SCHEMA_VERSION: i32 = 2;
+
+/// Columns added after the tables were first released, as table, column and type. Databases
+/// created before the version was stamped have any subset of them, so each is checked by name.
+/// Rows imported before a column existed hold NULL in it.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("games", "date", "TEXT"),
+    ("games", "hour", "INTEGER"),
+    ("games", "room", "TEXT"),
+    ("players", "dan_name", "TEXT"),
+    ("rounds", "outcome", "TEXT"),
+    ("rounds", "winners", "TEXT"),
+    ("rounds", "houjuu", "INTEGER"),
+    ("rounds", "renchan", "INTEGER"),
+    ("wins", "hora_order", "INTEGER"),
+];
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS games (
+    log_id TEXT PRIMARY KEY,
+    date TEXT,
+    hour INTEGER,
+    room TEXT,
+    type INTEGER NOT NULL,
+    lobby INTEGER,
+    rounds INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS players (
+    log_id TEXT NOT NULL REFERENCES games (log_id),
+    seat INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    dan INTEGER NOT NULL,
+    dan_name TEXT NOT NULL,
+    rate REAL NOT NULL,
+    sex TEXT NOT NULL,
+    owari INTEGER NOT NULL,
+    PRIMARY KEY (log_id, seat)
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    log_id TEXT NOT NULL REFERENCES games (log_id),
+    round INTEGER NOT NULL,
+    bakaze TEXT NOT NULL,
+    kyoku INTEGER NOT NULL,
+    honba INTEGER NOT NULL,
+    kyotaku INTEGER NOT NULL,
+    oya INTEGER NOT NULL,
+    dora_marker TEXT NOT NULL,
+    score0 INTEGER NOT NULL,
+    score1 INTEGER NOT NULL,
+    score2 INTEGER NOT NULL,
+    score3 INTEGER NOT NULL,
+    outcome TEXT,
+    winners TEXT,
+    houjuu INTEGER,
+    renchan INTEGER,
+    PRIMARY KEY (log_id, round)
+);
+CREATE TABLE IF NOT EXISTS events (
+    log_id TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    seq INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    actor INTEGER,
+    junme INTEGER,
+    pai TEXT,
+    tsumogiri INTEGER,
+    target INTEGER,
+    PRIMARY KEY (log_id, round, seq),
+    FOREIGN KEY (log_id, round) REFERENCES rounds (log_id, round)
+);
+CREATE TABLE IF NOT EXISTS melds (
+    log_id TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    seq INTEGER NOT NULL,
+    actor INTEGER NOT NULL,
+    junme INTEGER,
+    kind TEXT NOT NULL,
+    pai TEXT,
+    consumed TEXT NOT NULL,
+    target INTEGER,
+    PRIMARY KEY (log_id, round, seq),
+    FOREIGN KEY (log_id, round, seq) REFERENCES events (log_id, round, seq)
+);
+CREATE TABLE IF NOT EXISTS wins (
+    log_id TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    seq INTEGER NOT NULL,
+    actor INTEGER NOT NULL,
+    fromwho INTEGER NOT NULL,
+    hora_order INTEGER,
+    paowho INTEGER,
+    honba INTEGER NOT NULL,
+    kyotaku INTEGER NOT NULL,
+    junme INTEGER NOT NULL,
+    han INTEGER NOT NULL,
+    hu INTEGER NOT NULL,
+    score INTEGER NOT NULL,
+    yaku TEXT NOT NULL,
+    machi TEXT NOT NULL,
+    hai TEXT NOT NULL,
+    dora_marker TEXT NOT NULL,
+    ura_marker TEXT,
+    delta0 INTEGER NOT NULL,
+    delta1 INTEGER NOT NULL,
+    delta2 INTEGER NOT NULL,
+    delta3 INTEGER NOT NULL,
+    PRIMARY KEY (log_id, round, seq),
+    FOREIGN KEY (log_id, round, seq) REFERENCES events (log_id, round, seq)
+);
+CREATE INDEX IF NOT EXISTS players_name ON players (name);
+CREATE INDEX IF NOT EXISTS events_type ON events (type);
+CREATE INDEX IF NOT EXISTS melds_actor ON melds (actor, kind);
+CREATE INDEX IF NOT EXISTS wins_actor ON wins (actor);
+CREATE INDEX IF NOT EXISTS wins_fromwho ON wins (fromwho);
+";
+
+fn join_pai<'a>(pais: impl IntoIterator<Item = &'a Pai>) -> String {
+    pais.into_iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+    conn.query_row("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2", [table, column], |_| Ok(()))
+        .optional().unwrap().is_some()
+}
+
+/// Creates missing tables and adds the columns that older versions did not have. Columns are
+/// appended by `ALTER TABLE`, so inserts name their columns instead of relying on their order.
+fn migrate(conn: &Connection) {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+    if version > SCHEMA_VERSION {
+        panic!("the database has schema version {}, newer than version {} of this program", version, SCHEMA_VERSION);
+    }
+    if version == SCHEMA_VERSION {
+        return;
+    }
+    conn.execute_batch("BEGIN").unwrap();
+    conn.execute_batch(SCHEMA).unwrap();
+    for &(table, column, kind) in ADDED_COLUMNS {
+        if !has_column(conn, table, column) {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, kind)).unwrap();
+        }
+    }
+    conn.execute_batch(&format!("PRAGMA user_version = {}; COMMIT", SCHEMA_VERSION)).unwrap();
+}
+
+/// A normalized SQLite database of games, appended to across runs.
+/// Games whose log id is already present are skipped.
+pub struct SqliteDatabase {
+    conn: Connection,
+    pending: usize,
+}
+
+impl SqliteDatabase {
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;").unwrap();
+        migrate(&conn);
+        conn.execute_batch("BEGIN").unwrap();
+        Self { conn, pending: 0 }
+    }
+
+    pub fn contains(&self, log_id: &str) -> bool {
+        self.conn.query_row("SELECT 1 FROM games WHERE log_id = ?1", [log_id], |_| Ok(()))
+            .optional().unwrap().is_some()
+    }
+
+    /// Returns `false` if the game was already imported.
+    pub fn insert(&mut self, log_id: &str, game: &Game) -> bool {
+        if self.contains(log_id) {
+            return false;
+        }
+        self.insert_game(log_id, game);
+        self.pending += 1;
+        if self.pending >= GAMES_PER_TRANSACTION {
+            self.conn.execute_batch("COMMIT; BEGIN").unwrap();
+            self.pending = 0;
+        }
+        true
+    }
+
+    fn insert_game(&self, log_id: &str, game: &Game) {
+        let meta = game.meta.as_ref();
+        self.conn.prepare_cached("INSERT INTO games (log_id, date, hour, room, type, lobby, rounds) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)").unwrap()
+            .execute(params![
+                log_id, meta.map(|x| &x.date), meta.map(|x| x.hour), meta.map(|x| &x.room),
+                game.r#type, game.lobby, game.games.len(),
+            ]).unwrap();
+        let mut players = self.conn.prepare_cached("INSERT INTO players (log_id, seat, name, dan, dan_name, rate, sex, owari) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)").unwrap();
+        for seat in 0..4 {
+            players.execute(params![log_id, seat, game.id[seat], u8::from(game.dan[seat]), game.dan[seat].name(), game.rate[seat], game.sex[seat], game.owari[seat]]).unwrap();
+        }
+        let mut rounds = self.conn.prepare_cached(
+            "INSERT INTO rounds (log_id, round, bakaze, kyoku, honba, kyotaku, oya, dora_marker, score0, score1, score2, score3, outcome, winners, houjuu, renchan)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        ).unwrap();
+        for (round_idx, round) in game.games.iter().enumerate() {
+            let data = &round.data;
+            let outcome = round.outcome();
+            rounds.execute(params![
+                log_id, round_idx, data.bakaze, data.kyoku, data.honba, data.kyotaku, data.oya, data.dora_marker.to_string(),
+                data.scores[0], data.scores[1], data.scores[2], data.scores[3],
+                outcome.as_ref().map(|x| x.kind.name()),
+                outcome.as_ref().map(|x| x.winners.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")),
+                outcome.as_ref().and_then(|x| x.houjuu), outcome.as_ref().map(|x| x.renchan),
+            ]).unwrap();
+            for ((seq, event), hora_order) in round.game.iter().enumerate().zip(round.hora_orders()) {
+                self.insert_event(log_id, round_idx, seq, event, hora_order);
+            }
+        }
+    }
+
+    fn insert_event(&self, log_id: &str, round: usize, seq: usize, event: &MajEvent, hora_order: Option<u8>) {
+        self.conn.prepare_cached("INSERT INTO events (log_id, round, seq, type, actor, junme, pai, tsumogiri, target) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)").unwrap()
+            .execute(params![
+                log_id, round, seq, event.type_name(), event.actor(), event.junme(),
+                event.pai().map(|x| x.to_string()), event.tsumogiri(), event.target(),
+            ]).unwrap();
+
+        match event {
+            MajEvent::Naki { junme, actor, consumed, pai, target, kind } => {
+                self.conn.prepare_cached("INSERT INTO melds (log_id, round, seq, actor, junme, kind, pai, consumed, target) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)").unwrap()
+                    .execute(params![log_id, round, seq, actor, junme, kind.name(), pai.map(|x| x.to_string()), join_pai(consumed), target]).unwrap();
+            }
+            MajEvent::Agari { honba, kyotaku, junme, hai, machi, han, hu, score, yaku, dora_marker, ura_marker, actor, fromwho, paowho, deltas, .. } => {
+                let yaku = yaku.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",");
+                self.conn.prepare_cached("INSERT INTO wins (log_id, round, seq, actor, fromwho, hora_order, paowho, honba, kyotaku, junme, han, hu, score, yaku,
+                    machi, hai, dora_marker, ura_marker, delta0, delta1, delta2, delta3)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)").unwrap()
+                    .execute(params![
+                        log_id, round, seq, actor, fromwho, hora_order, paowho, honba, kyotaku, junme, han, hu, score, yaku,
+                        machi.to_string(), join_pai(hai), join_pai(dora_marker), ura_marker.as_ref().map(join_pai),
+                        deltas[0], deltas[1], deltas[2], deltas[3],
+                    ]).unwrap();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn finish(self) {
+        self.conn.execute_batch("COMMIT").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+    use super::{SqliteDatabase, SCHEMA_VERSION};
+    use crate::testing::{fixture, temp_dir};
+
+    #[test]
+    fn database_of_an_older_version_is_migrated() {
+        let path = temp_dir("sqlite-migrate").join("games.sqlite");
+        // The tables of the first release, before the version was stamped.
+        Connection::open(&path).unwrap().execute_batch("
+            CREATE TABLE games (log_id TEXT PRIMARY KEY, type INTEGER NOT NULL, lobby INTEGER, rounds INTEGER NOT NULL);
+            CREATE TABLE players (log_id TEXT NOT NULL, seat INTEGER NOT NULL, name TEXT NOT NULL, dan INTEGER NOT NULL, rate REAL NOT NULL, sex TEXT NOT NULL, owari INTEGER NOT NULL, PRIMARY KEY (log_id, seat));
+            INSERT INTO games VALUES ('old', 169, 0, 8);
+        ").unwrap();
+
+        let mut db = SqliteDatabase::open(&path);
+        assert!(db.contains("old"));
+        assert!(db.insert("new", &fixture()));
+        db.finish();
+
+        let conn = Connection::open(&path).unwrap();
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        let rounds: usize = conn.query_row("SELECT rounds FROM games WHERE log_id = 'new'", [], |row| row.get(0)).unwrap();
+        assert_eq!(rounds, fixture().games.len());
+        let outcomes: usize = conn.query_row("SELECT count(outcome) FROM rounds WHERE log_id = 'new'", [], |row| row.get(0)).unwrap();
+        assert_eq!(outcomes, rounds);
+    }
+
+    #[test]
+    #[should_panic(expected = "newer than version")]
+    fn database_of_a_newer_version_is_refused() {
+        let path = temp_dir("sqlite-newer").join("games.sqlite");
+        Connection::open(&path).unwrap().execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1)).unwrap();
+        SqliteDatabase::open(&path);
+    }
+}
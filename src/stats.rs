@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::io::Write;
+use serde::Serialize;
+use crate::enums::{MajEvent, NakiKind, ReachStep, RyuukyokuReason, ANONYMOUS};
+use crate::round::Game;
+
+/// Counters for one player, summed over all games added to a `StatsAggregator`.
+#[derive(Debug, Default, Clone)]
+pub struct PlayerStats {
+    pub name: String,
+    pub games: u32,
+    pub rounds: u32,
+    /// Rounds won.
+    pub agari: u32,
+    /// Rounds dealt in, counted once on a double ron.
+    pub houjuu: u32,
+    pub riichi: u32,
+    /// Rounds with at least one chii, pon or daiminkan.
+    pub called: u32,
+    /// Sum of the hand values of the wins, without honba and riichi sticks.
+    pub agari_score: i64,
+    /// Sum of the hand values dealt in.
+    pub houjuu_score: i64,
+    /// Draws where hands are shown, exhaustive draws and nagashi mangan.
+    pub ryuukyoku: u32,
+    pub ryuukyoku_tenpai: u32,
+    /// Games finished in 1st to 4th place.
+    pub placements: [u32; 4],
+    /// Rate at the start of every game, in the order the games were added.
+    pub rates: Vec<f32>,
+}
+
+/// Rates of a player, the row of the CSV output. The rate is `None` for a player without games.
+#[derive(Debug, Serialize)]
+pub struct PlayerSummary {
+    pub name: String,
+    pub games: u32,
+    pub rounds: u32,
+    pub agari_rate: f64,
+    pub houjuu_rate: f64,
+    pub riichi_rate: f64,
+    pub call_rate: f64,
+    pub average_agari_score: f64,
+    pub average_houjuu_score: f64,
+    pub ryuukyoku_tenpai_rate: f64,
+    pub average_placement: f64,
+    pub first: u32,
+    pub second: u32,
+    pub third: u32,
+    pub fourth: u32,
+    pub first_rate: Option<f32>,
+    pub last_rate: Option<f32>,
+    pub max_rate: Option<f32>,
+}
+
+/// A player of the JSON output, the summary followed by the rate at the start of every game.
+#[derive(Serialize)]
+struct PlayerReport<'a> {
+    #[serde(flatten)]
+    summary: PlayerSummary,
+    rates: &'a [f32],
+}
+
+fn ratio(a: impl Into<f64>, b: impl Into<f64>) -> f64 {
+    let b = b.into();
+    if b == 0.0 { 0.0 } else { a.into() / b }
+}
+
+impl PlayerStats {
+    pub fn summary(&self) -> PlayerSummary {
+        let placed: u32 = self.placements.iter().sum();
+        let placement_sum: u32 = self.placements.iter().zip(1..).map(|(count, place)| count * place).sum();
+        PlayerSummary {
+            name: self.name.clone(),
+            games: self.games,
+            rounds: self.rounds,
+            agari_rate: ratio(self.agari, self.rounds),
+            houjuu_rate: ratio(self.houjuu, self.rounds),
+            riichi_rate: ratio(self.riichi, self.rounds),
+            call_rate: ratio(self.called, self.rounds),
+            average_agari_score: ratio(self.agari_score as f64, self.agari),
+            average_houjuu_score: ratio(self.houjuu_score as f64, self.houjuu),
+            ryuukyoku_tenpai_rate: ratio(self.ryuukyoku_tenpai, self.ryuukyoku),
+            average_placement: ratio(placement_sum, placed),
+            first: self.placements[0],
+            second: self.placements[1],
+            third: self.placements[2],
+            fourth: self.placements[3],
+            first_rate: self.rates.first().copied(),
+            last_rate: self.rates.last().copied(),
+            max_rate: self.rates.iter().copied().reduce(f32::max),
+        }
+    }
+}
+
+/// Places from the final scores. Ties go to the player seated earlier, as on Tenhou.
+/// `None` if the log has no `owari`, as for a game that was cut off.
+pub fn placements(game: &Game) -> Option<[u8; 4]> {
+    if game.owari == [0; 4] {
+        return None;
+    }
+    let mut seats = [0, 1, 2, 3];
+    seats.sort_by_key(|&seat| std::cmp::Reverse(game.owari[seat]));
+    let mut places = [0; 4];
+    for (place, seat) in seats.into_iter().enumerate() {
+        places[seat] = place as u8 + 1;
+    }
+    Some(places)
+}
+
+/// Per-player statistics over many games, keyed by player name.
+/// `NoName` players and empty seats are left out.
+#[derive(Debug, Default)]
+pub struct StatsAggregator {
+    players: HashMap<String, PlayerStats>,
+}
+
+impl StatsAggregator {
+    pub fn add(&mut self, game: &Game) {
+        let places = placements(game);
+        let seats: Vec<usize> = (0..4).filter(|&seat| !game.id[seat].is_empty() && game.id[seat] != ANONYMOUS).collect();
+        for &seat in &seats {
+            let stats = self.players.entry(game.id[seat].clone()).or_insert_with(|| PlayerStats {
+                name: game.id[seat].clone(),
+                ..Default::default()
+            });
+            stats.games += 1;
+            stats.rounds += game.games.len() as u32;
+            if let Some(places) = places {
+                stats.placements[places[seat] as usize - 1] += 1;
+            }
+            stats.rates.push(game.rate[seat]);
+        }
+
+        for round in &game.games {
+            let mut agari = [false; 4];
+            let mut houjuu = [false; 4];
+            let mut riichi = [false; 4];
+            let mut called = [false; 4];
+            let mut agari_score = [0; 4];
+            let mut houjuu_score = [0; 4];
+            let mut shown = false;
+            let mut tenpai = [false; 4];
+            for event in &round.game {
+                match event {
+                    MajEvent::Reach { actor, step: ReachStep::Riichi, .. } => riichi[*actor as usize] = true,
+                    MajEvent::Naki { actor, kind: NakiKind::Chii | NakiKind::Pon | NakiKind::Daiminkan, .. } => {
+                        called[*actor as usize] = true;
+                    }
+                    MajEvent::Agari { actor, fromwho, score, .. } => {
+                        agari[*actor as usize] = true;
+                        agari_score[*actor as usize] += *score as i64;
+                        if fromwho != actor {
+                            houjuu[*fromwho as usize] = true;
+                            houjuu_score[*fromwho as usize] += *score as i64;
+                        }
+                    }
+                    MajEvent::Ryuukyoku { reason: RyuukyokuReason::Howanpai | RyuukyokuReason::Nm, tenpai: shown_tenpai, .. } => {
+                        shown = true;
+                        tenpai = *shown_tenpai;
+                    }
+                    _ => {}
+                }
+            }
+            for &seat in &seats {
+                let stats = self.players.get_mut(&game.id[seat]).unwrap();
+                stats.agari += agari[seat] as u32;
+                stats.houjuu += houjuu[seat] as u32;
+                stats.riichi += riichi[seat] as u32;
+                stats.called += called[seat] as u32;
+                stats.agari_score += agari_score[seat];
+                stats.houjuu_score += houjuu_score[seat];
+                stats.ryuukyoku += shown as u32;
+                stats.ryuukyoku_tenpai += tenpai[seat] as u32;
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerStats> {
+        self.players.get(name)
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &PlayerStats> {
+        self.players.values()
+    }
+
+    /// Players with at least `min_games` games, most games first.
+    fn selected(&self, min_games: u32) -> Vec<&PlayerStats> {
+        let mut players: Vec<&PlayerStats> = self.players().filter(|x| x.games >= min_games).collect();
+        players.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.name.cmp(&b.name)));
+        players
+    }
+
+    /// Summaries of the players with at least `min_games` games, most games first.
+    pub fn summaries(&self, min_games: u32) -> Vec<PlayerSummary> {
+        self.selected(min_games).into_iter().map(|x| x.summary()).collect()
+    }
+
+    /// Like the CSV, with the rate at the start of every game in `rates`.
+    pub fn write_json<W: Write>(&self, writer: W, min_games: u32) {
+        let reports: Vec<PlayerReport> = self.selected(min_games).into_iter().map(|x| PlayerReport { summary: x.summary(), rates: &x.rates }).collect();
+        serde_json::to_writer_pretty(writer, &reports).unwrap();
+    }
+
+    pub fn write_csv<W: Write>(&self, writer: W, min_games: u32) {
+        let mut writer = csv::Writer::from_writer(writer);
+        for summary in self.summaries(min_games) {
+            writer.serialize(summary).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{placements, PlayerStats, StatsAggregator};
+    use crate::testing::fixture;
+
+    #[test]
+    fn placements_follow_owari_with_ties_to_the_earlier_seat() {
+        let mut game = fixture();
+        game.owari = [250, 300, 250, 200];
+        assert_eq!(placements(&game), Some([2, 1, 3, 4]));
+    }
+
+    #[test]
+    fn json_has_the_rate_of_every_game_and_csv_the_summary() {
+        let mut stats = StatsAggregator::default();
+        let mut game = fixture();
+        stats.add(&game);
+        game.rate[0] += 20.0;
+        stats.add(&game);
+        game.rate[0] -= 50.0;
+        stats.add(&game);
+        let name = &game.id[0];
+        let rate = fixture().rate[0];
+
+        let mut json = Vec::new();
+        stats.write_json(&mut json, 0);
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let player = json.as_array().unwrap().iter().find(|x| x["name"] == name.as_str()).unwrap();
+        assert_eq!(player["rates"], serde_json::json!([rate, rate + 20.0, rate - 30.0]));
+        assert_eq!(player["first_rate"], serde_json::json!(rate));
+        assert_eq!(player["last_rate"], serde_json::json!(rate - 30.0));
+        assert_eq!(player["max_rate"], serde_json::json!(rate + 20.0));
+
+        let mut csv = Vec::new();
+        stats.write_csv(&mut csv, 0);
+        let csv = String::from_utf8(csv).unwrap();
+        let header = csv.lines().next().unwrap();
+        assert!(header.ends_with("first_rate,last_rate,max_rate"), "{}", header);
+        assert_eq!(csv.lines().count(), 1 + stats.summaries(0).len());
+    }
+
+    #[test]
+    fn player_without_games_has_no_rate() {
+        let summary = PlayerStats::default().summary();
+        assert_eq!((summary.first_rate, summary.last_rate, summary.max_rate), (None, None, None));
+    }
+
+    #[test]
+    fn games_without_owari_are_not_placed() {
+        let mut game = fixture();
+        game.owari = [0; 4];
+        assert_eq!(placements(&game), None);
+
+        let mut stats = StatsAggregator::default();
+        stats.add(&game);
+        stats.add(&fixture());
+        let player = stats.get(&game.id[0]).unwrap();
+        assert_eq!(player.games, 2);
+        assert_eq!(player.placements.iter().sum::<u32>(), 1);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::Write;
+use crate::board::{Board, Meld};
+use crate::enums::{NakiKind, Pai, PaiColor};
+use crate::river::RiverTile;
+use crate::round::Game;
+
+const SIZE: i32 = 800;
+const CENTER: i32 = SIZE / 2;
+const TILE_W: i32 = 30;
+const TILE_H: i32 = 40;
+/// Tiles per river row, the last row takes the rest.
+const RIVER_ROW: usize = 6;
+const RIVER_ROWS: usize = 3;
+
+fn label(pai: Pai) -> String {
+    match pai.color {
+        PaiColor::Jihai => ["東", "南", "西", "北", "白", "發", "中"][pai.num as usize - 1].to_string(),
+        _ => format!("{}{}", pai.num, pai.to_string().chars().last().unwrap()),
+    }
+}
+
+fn is_red(pai: Pai) -> bool {
+    pai.color != PaiColor::Jihai && pai.num == 5 && pai.idx == 0
+}
+
+struct Tile {
+    pai: Option<Pai>,
+    sideways: bool,
+    dim: bool,
+}
+
+impl Tile {
+    fn up(pai: Pai) -> Self {
+        Self { pai: Some(pai), sideways: false, dim: false }
+    }
+
+    fn width(&self) -> i32 {
+        if self.sideways { TILE_H } else { TILE_W }
+    }
+
+    /// Draws the tile with its bottom edge at `bottom`, so sideways tiles line up with upright ones.
+    fn draw(&self, svg: &mut String, x: i32, bottom: i32) {
+        let (w, h) = if self.sideways { (TILE_H, TILE_W) } else { (TILE_W, TILE_H) };
+        let fill = match (self.pai, self.dim) {
+            (None, _) => "#2e6b4f",
+            (Some(_), true) => "#dddddd",
+            (Some(_), false) => "#ffffff",
+        };
+        write!(svg, r##"<rect x="{}" y="{}" width="{}" height="{}" rx="3" fill="{}" stroke="#333"/>"##, x, bottom - h, w, h, fill).unwrap();
+        if let Some(pai) = self.pai {
+            let color = if is_red(pai) { "#d01010" } else { "#111111" };
+            write!(svg, r#"<text x="{}" y="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#, x + w / 2, bottom - h / 2, color, label(pai)).unwrap();
+        }
+    }
+}
+
+fn river_tile(tile: &RiverTile) -> Tile {
+    Tile { pai: Some(tile.pai), sideways: tile.sideways, dim: tile.tsumogiri }
+}
+
+/// The tiles of a meld from left to right. The called tile lies sideways on the side of the
+/// player it came from, and the tile added by a kakan lies on top of it. A kita is one upright tile.
+fn meld_tiles(meld: &Meld, actor: u8) -> Vec<(Tile, Option<Tile>)> {
+    match meld.kind {
+        NakiKind::Nukidora => meld.tiles.iter().map(|&x| (Tile::up(x), None)).collect(),
+        NakiKind::Ankan => meld.tiles.iter().enumerate().map(|(i, &x)| {
+            let pai = if i == 0 || i == 3 { None } else { Some(x) };
+            (Tile { pai, sideways: false, dim: false }, None)
+        }).collect(),
+        _ => {
+            let own = if meld.kind == NakiKind::Kakan { 2 } else { meld.tiles.len() - 1 };
+            let mut tiles: Vec<(Tile, Option<Tile>)> = meld.tiles[..own].iter().map(|&x| (Tile::up(x), None)).collect();
+            let called = Tile { pai: meld.called, sideways: true, dim: false };
+            let added = meld.tiles.get(own + 1).map(|&x| Tile { pai: Some(x), sideways: true, dim: false });
+            let position = match meld.target.map(|x| (x + 4 - actor) % 4) {
+                Some(3) => 0,
+                Some(2) => 1,
+                _ => tiles.len(),
+            };
+            tiles.insert(position, (called, added));
+            tiles
+        }
+    }
+}
+
+/// Draws one seat as if it sat at the bottom edge. The caller rotates it into place.
+fn draw_seat(svg: &mut String, game: &Game, board: &Board, seat: usize, dealer: bool) {
+    let mut name = format!("{} {} {}", game.id[seat], game.dan[seat], board.scores[seat]);
+    if dealer {
+        name.insert_str(0, "[親] ");
+    }
+    write!(svg, r##"<text x="{}" y="{}" text-anchor="middle" fill="#ffffff">{}</text>"##, CENTER, CENTER + 105, escape(&name)).unwrap();
+    if board.riichi[seat] {
+        write!(svg, r##"<rect x="{}" y="{}" width="100" height="8" rx="4" fill="#ffffff" stroke="#333"/><circle cx="{}" cy="{}" r="3" fill="#d01010"/>"##,
+               CENTER - 50, CENTER + 80, CENTER, CENTER + 84).unwrap();
+    }
+
+    let left = CENTER - RIVER_ROW as i32 * TILE_W / 2;
+    let (mut x, mut row) = (left, 0);
+    for (i, tile) in board.rivers()[seat].visible().enumerate() {
+        if i > 0 && i % RIVER_ROW == 0 && row + 1 < RIVER_ROWS {
+            x = left;
+            row += 1;
+        }
+        let tile = river_tile(tile);
+        tile.draw(svg, x, CENTER + 130 + TILE_H * (row as i32 + 1));
+        x += tile.width();
+    }
+
+    let bottom = SIZE - 20;
+    let mut x = 40;
+    for &pai in &board.hands[seat] {
+        Tile::up(pai).draw(svg, x, bottom);
+        x += TILE_W;
+    }
+    if let Some(pai) = board.drawn[seat] {
+        Tile::up(pai).draw(svg, x + 10, bottom);
+    }
+
+    let mut x = SIZE - 60;
+    for meld in board.melds[seat].iter().rev() {
+        let tiles = meld_tiles(meld, seat as u8);
+        x -= tiles.iter().map(|(x, _)| x.width()).sum::<i32>();
+        let mut tile_x = x;
+        for (tile, added) in &tiles {
+            tile.draw(svg, tile_x, bottom);
+            if let Some(added) = added {
+                added.draw(svg, tile_x, bottom - TILE_W);
+            }
+            tile_x += tile.width();
+        }
+        x -= 8;
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Draws the table of `game.games[round]` before the event at `event`, as in `Round::board_at`.
+/// All hands are shown open with seat 0 at the bottom, and seats 1 to 3 follow counterclockwise.
+pub fn board_svg(game: &Game, round: usize, event: usize) -> String {
+    let round = &game.games[round];
+    let board = round.board_at(event);
+    let data = &round.data;
+
+    let mut svg = String::new();
+    write!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}" font-family="sans-serif" font-size="16">"#, SIZE).unwrap();
+    write!(svg, r##"<rect width="{0}" height="{0}" fill="#3d8c68"/>"##, SIZE).unwrap();
+    write!(svg, r##"<rect x="{0}" y="{0}" width="240" height="240" fill="#2e6b4f" stroke="#1e4a36"/>"##, CENTER - 120).unwrap();
+
+    let wind = match data.bakaze.as_str() {
+        "E" => "東",
+        "S" => "南",
+        "W" => "西",
+        _ => "北",
+    };
+    write!(svg, r##"<text x="{}" y="{}" text-anchor="middle" fill="#ffffff" font-size="22">{}{}局 {}本場</text>"##, CENTER, CENTER - 40, wind, data.kyoku, data.honba).unwrap();
+    write!(svg, r##"<text x="{}" y="{}" text-anchor="middle" fill="#ffffff">供託 {}</text>"##, CENTER, CENTER - 15, board.kyotaku).unwrap();
+    let left = CENTER - board.dora_markers.len() as i32 * TILE_W / 2;
+    for (i, &pai) in board.dora_markers.iter().enumerate() {
+        Tile::up(pai).draw(&mut svg, left + i as i32 * TILE_W, CENTER + 45);
+    }
+
+    for seat in 0..4 {
+        write!(svg, r#"<g transform="rotate({} {} {})">"#, -90 * seat as i32, CENTER, CENTER).unwrap();
+        draw_seat(&mut svg, game, &board, seat, data.oya as usize == seat);
+        svg.push_str("</g>");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{board_svg, meld_tiles};
+    use crate::board::{Board, Meld};
+    use crate::enums::{MajEvent, NakiKind, Pai, ReachStep, RyuukyokuReason};
+    use crate::round::Game;
+    use crate::testing::{fixture, RoundBuilder};
+
+    const SIDEWAYS: &str = r#"width="40" height="30""#;
+
+    /// Tiles on the board and how many of them lie sideways, counting dora markers and kakan tiles.
+    fn count_tiles(board: &Board) -> (usize, usize) {
+        let (mut all, mut sideways) = (board.dora_markers.len(), 0);
+        for seat in 0..4 {
+            all += board.hands[seat].len() + board.drawn[seat].iter().count();
+            for tile in board.rivers()[seat].visible() {
+                all += 1;
+                sideways += tile.sideways as usize;
+            }
+            for meld in &board.melds[seat] {
+                for (tile, added) in meld_tiles(meld, seat as u8) {
+                    for tile in [Some(tile), added].into_iter().flatten() {
+                        all += 1;
+                        sideways += tile.sideways as usize;
+                    }
+                }
+            }
+        }
+        (all, sideways)
+    }
+
+    #[test]
+    fn board_after_a_riichi_declaration() {
+        let game = fixture();
+        let (round, event) = game.games.iter().enumerate().find_map(|(r, round)| {
+            let i = round.game.windows(2).position(|x| matches!(x, [MajEvent::Reach { step: ReachStep::Riichi, .. }, MajEvent::Dahai { .. }]))?;
+            Some((r, i + 2))
+        }).unwrap();
+        let board = game.games[round].board_at(event);
+        let MajEvent::Dahai { actor, pai, .. } = game.games[round].game[event - 1] else { unreachable!() };
+        let declared = board.rivers()[actor as usize].visible().last().unwrap();
+        assert!(declared.sideways && declared.pai == pai);
+
+        let svg = board_svg(&game, round, event);
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        let (all, sideways) = count_tiles(&board);
+        assert_eq!(svg.matches(r#"rx="3""#).count(), all);
+        assert!(sideways >= 1);
+        assert_eq!(svg.matches(SIDEWAYS).count(), sideways);
+        // The riichi stick is drawn once the declaration tile is discarded.
+        assert_eq!(svg.matches(r#"rx="4""#).count(), board.riichi.iter().filter(|&&x| x).count());
+        assert!(board.riichi[actor as usize]);
+    }
+
+    #[test]
+    fn kita_stands_upright_next_to_the_hand() {
+        let mut b = RoundBuilder::new(0, ["123456789m1234z", "123456789p1177z", "123456789s1235m", "2223335556667z"]);
+        let drawn = b.tsumo(0, "9s");
+        b.naki(0, NakiKind::Nukidora, &b.dealt(0, "4z"), None, None);
+        b.tsumo(0, "6z");
+        b.dahai(0, drawn);
+        b.ryuukyoku(RyuukyokuReason::Howanpai, [false; 4], [0; 4]);
+        let game = Game { games: vec![b.build()], ..Default::default() };
+        let event = game.games[0].game.len() - 1;
+
+        let svg = board_svg(&game, 0, event);
+        let board = game.games[0].board_at(event);
+        assert_eq!(svg.matches(r#"rx="3""#).count(), count_tiles(&board).0);
+        assert_eq!(svg.matches(SIDEWAYS).count(), 0);
+        // The only north wind on the table is the kita, drawn upright.
+        assert_eq!(svg.matches("北</text>").count(), 1);
+        let kita = svg.find("北</text>").unwrap();
+        let rect = &svg[svg[..kita].rfind("<rect").unwrap()..kita];
+        assert!(rect.contains(r#"width="30" height="40""#), "{}", rect);
+    }
+
+    fn faces(meld: &Meld, actor: u8) -> Vec<(Option<Pai>, bool)> {
+        meld_tiles(meld, actor).iter().map(|(x, _)| (x.pai, x.sideways)).collect()
+    }
+
+    #[test]
+    fn kita_is_drawn_face_up() {
+        let kita = Pai::from(120);
+        let meld = Meld { kind: NakiKind::Nukidora, tiles: vec![kita], called: None, target: None };
+        assert_eq!(faces(&meld, 0), [(Some(kita), false)]);
+    }
+
+    #[test]
+    fn called_tile_lies_on_the_side_of_the_discarder() {
+        let [a, b, c] = [Pai::from(124), Pai::from(125), Pai::from(126)];
+        let meld = Meld { kind: NakiKind::Pon, tiles: vec![a, b, c], called: Some(c), target: Some(3) };
+        assert_eq!(faces(&meld, 0), [(Some(c), true), (Some(a), false), (Some(b), false)]);
+        let meld = Meld { target: Some(1), ..meld };
+        assert_eq!(faces(&meld, 0), [(Some(a), false), (Some(b), false), (Some(c), true)]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::PathBuf;
+use crate::enums::{MajEvent, NakiKind, Pai, ReachStep, RyuukyokuReason};
+use crate::round::{Game, Round, RoundData};
+
+/// A log in Tenhou's format with every kind of call, riichi, tsumo, ron, two double rons and an exhaustive draw.
+pub(crate) const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/2023020215gm-00a9-0000-6d3f2b1c.xml");
+
+pub(crate) fn fixture() -> Game {
+    Game::parse_xml_file(FIXTURE)
+}
+
+/// An empty directory for the files written by one test.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tenhou-parser-rs-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Tile names of a hand such as `123m11z`.
+fn names(hand: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut nums = String::new();
+    for c in hand.chars() {
+        if c.is_ascii_digit() {
+            nums.push(c);
+        } else {
+            names.extend(std::mem::take(&mut nums).chars().map(|num| format!("{}{}", num, c)));
+        }
+    }
+    names
+}
+
+/// A round built by hand for the tests of the replay logic. Tiles are named as in the JSON output,
+/// and each name gets a copy that was not handed out before, so tile ids never repeat.
+pub(crate) struct RoundBuilder {
+    round: Round,
+    used: [bool; 136],
+}
+
+impl RoundBuilder {
+    /// Deals hands written like `123m456p789s1122z` in East `oya + 1` with 25000 points each.
+    /// The dora marker is a copy of 9p.
+    pub(crate) fn new(oya: u8, tehais: [&str; 4]) -> Self {
+        let mut builder = Self { round: Round::default(), used: [false; 136] };
+        let tehais = tehais.map(|hand| builder.tiles(hand).try_into().unwrap());
+        builder.round.data = RoundData {
+            bakaze: "E".to_string(),
+            dora_marker: builder.tile("9p"),
+            kyoku: oya + 1,
+            oya,
+            scores: [25000; 4],
+            tehais,
+            ..Default::default()
+        };
+        builder
+    }
+
+    /// A copy of the tile not handed out before. `0m`, `0p` and `0s` are the red fives.
+    pub(crate) fn tile(&mut self, name: &str) -> Pai {
+        let (num, color) = name.split_at(1);
+        let num: u8 = num.parse().unwrap();
+        let base = match color {
+            "m" => 0,
+            "p" => 9,
+            "s" => 18,
+            "z" => 27,
+            _ => panic!("Invalid tile {}", name),
+        };
+        let copies = match (num, color) {
+            (0, _) => 0..1,
+            (5, "m" | "p" | "s") => 1..4,
+            _ => 0..4,
+        };
+        let first = (base + if num == 0 { 5 } else { num } - 1) * 4;
+        let id = copies.map(|x| first + x).find(|&x| !self.used[x as usize]).unwrap_or_else(|| panic!("No copy of {} left", name));
+        self.used[id as usize] = true;
+        Pai::from(id)
+    }
+
+    /// Fresh copies of the tiles of a hand such as `123m456p789s1122z`.
+    pub(crate) fn tiles(&mut self, hand: &str) -> Vec<Pai> {
+        names(hand).iter().map(|x| self.tile(x)).collect()
+    }
+
+    /// Tiles dealt to `actor`, written like `77z`, each a different copy.
+    pub(crate) fn dealt(&self, actor: u8, tiles: &str) -> Vec<Pai> {
+        let mut dealt: Vec<Pai> = Vec::new();
+        for name in names(tiles) {
+            let pai = self.round.data.tehais[actor as usize].iter().find(|x| x.to_string() == name && !dealt.contains(x));
+            dealt.push(*pai.unwrap_or_else(|| panic!("{} was not dealt to player {}", name, actor)));
+        }
+        dealt
+    }
+
+    pub(crate) fn tsumo(&mut self, actor: u8, name: &str) -> Pai {
+        let pai = self.tile(name);
+        self.round.junme[actor as usize] += 1;
+        self.round.game.push(MajEvent::Tsumo { junme: self.round.junme[actor as usize], actor, pai });
+        pai
+    }
+
+    /// Whether `pai` is the tile drawn last, as the parser tells tsumogiri.
+    fn is_drawn(&self, pai: Pai) -> bool {
+        self.round.game.iter().rev().find_map(|x| match x {
+            MajEvent::Tsumo { pai: drawn, .. } => Some(*drawn == pai),
+            MajEvent::Dahai { .. } => Some(false),
+            _ => None,
+        }).unwrap_or(false)
+    }
+
+    pub(crate) fn dahai(&mut self, actor: u8, pai: Pai) {
+        let tsumogiri = self.is_drawn(pai);
+        self.round.game.push(MajEvent::Dahai { junme: self.round.junme[actor as usize], actor, pai, tsumogiri });
+    }
+
+    /// Declares riichi, discards `pai` and puts the stick down.
+    pub(crate) fn riichi(&mut self, actor: u8, pai: Pai) {
+        let junme = self.round.junme[actor as usize];
+        let tsumogiri = self.is_drawn(pai);
+        self.round.game.push(MajEvent::Reach { junme, actor, step: ReachStep::Riichi, pai: Some(pai), tsumogiri: Some(tsumogiri), scores: None });
+        self.dahai(actor, pai);
+        self.round.game.push(MajEvent::Reach { junme, actor, step: ReachStep::RiichiAccepted, pai: None, tsumogiri: None, scores: None });
+    }
+
+    /// `consumed` and `pai` as `decode_naki` gives them for `kind`.
+    pub(crate) fn naki(&mut self, actor: u8, kind: NakiKind, consumed: &[Pai], pai: Option<Pai>, target: Option<u8>) {
+        self.round.junme[actor as usize] += 1;
+        let junme = Some(self.round.junme[actor as usize]);
+        self.round.game.push(MajEvent::Naki { junme, actor, consumed: consumed.to_vec(), pai, target, kind });
+    }
+
+    pub(crate) fn dora(&mut self, name: &str) -> Pai {
+        let dora_marker = self.tile(name);
+        self.round.game.push(MajEvent::Dora { dora_marker });
+        dora_marker
+    }
+
+    /// A 1 han 30 fu win on the hand the replay holds. Only the first of several rons gets the sticks.
+    pub(crate) fn agari(&mut self, actor: u8, fromwho: u8, machi: Pai, score: i32, deltas: [i32; 4]) {
+        let board = self.round.board_at(self.round.game.len());
+        let mut hai = board.hands[actor as usize].clone();
+        hai.push(machi);
+        let first = !self.round.game.iter().any(|x| matches!(x, MajEvent::Agari { .. }));
+        self.round.game.push(MajEvent::Agari {
+            honba: if first { self.round.data.honba } else { 0 },
+            kyotaku: board.kyotaku,
+            junme: self.round.junme[actor as usize],
+            hai,
+            naki: None,
+            machi,
+            han: 1,
+            hu: 30,
+            score,
+            yaku: vec![1],
+            dora_marker: board.dora_markers,
+            ura_marker: None,
+            actor,
+            fromwho,
+            paowho: None,
+            deltas,
+        });
+    }
+
+    pub(crate) fn ryuukyoku(&mut self, reason: RyuukyokuReason, tenpai: [bool; 4], deltas: [i32; 4]) {
+        self.round.game.push(MajEvent::Ryuukyoku { reason, tehais: Default::default(), tenpai, deltas, nagashi: [false; 4] });
+    }
+
+    pub(crate) fn build(self) -> Round {
+        self.round
+    }
+}
@@ -0,0 +1,185 @@
+use crate::enums::{MajEvent, NakiKind, Pai, ReachStep, RyuukyokuReason};
+use crate::round::{Game, Round};
+use crate::yaku::yaku_name;
+
+fn wind_name(bakaze: &str) -> &'static str {
+    match bakaze {
+        "E" => "East",
+        "S" => "South",
+        "W" => "West",
+        _ => "North",
+    }
+}
+
+fn tiles(tiles: &[Pai]) -> String {
+    tiles.iter().map(|x| x.to_string()).collect()
+}
+
+/// Yaku in the order of the log, with repeated dora counted, e.g. `riichi, pinfu, dora 2`.
+fn yaku_list(yaku: &[u8]) -> String {
+    let mut counts: Vec<(u8, usize)> = Vec::new();
+    for &y in yaku {
+        match counts.iter_mut().find(|x| x.0 == y) {
+            Some(count) => count.1 += 1,
+            None => counts.push((y, 1)),
+        }
+    }
+    counts.iter().map(|&(y, n)| {
+        if n > 1 { format!("{} {}", yaku_name(y), n) } else { yaku_name(y).to_string() }
+    }).collect::<Vec<String>>().join(", ")
+}
+
+fn deltas(deltas: &[i32; 4]) -> String {
+    let changes = (0..4).filter(|&i| deltas[i] != 0).map(|i| format!("S{} {:+}", i, deltas[i])).collect::<Vec<String>>();
+    if changes.is_empty() { "no score change".to_string() } else { changes.join(", ") }
+}
+
+fn seats(flags: &[bool; 4]) -> String {
+    (0..4).filter(|&i| flags[i]).map(|i| format!("S{}", i)).collect::<Vec<String>>().join(" ")
+}
+
+/// Appends to the line of the turn of `actor`, or starts a line naming the player if the last line is not theirs.
+fn append(lines: &mut Vec<String>, turn: &mut Option<u8>, actor: u8, text: &str) {
+    if *turn == Some(actor) {
+        lines.last_mut().unwrap().push_str(&format!(", {}", text));
+    } else {
+        lines.push(format!("S{} {}", actor, text));
+        *turn = Some(actor);
+    }
+}
+
+/// One line per turn: a draw or call starts a line, and the discard, riichi and new dora markers
+/// that follow are appended to it. Anything in between, such as a disconnect, ends the turn.
+fn round_lines(round: &Round) -> Vec<String> {
+    let data = &round.data;
+    let mut lines = vec![
+        format!("{} {}, {} honba — dealer seat {}", wind_name(&data.bakaze), data.kyoku, data.honba, data.oya),
+        format!(
+            "riichi sticks {}, dora marker {}, scores {}",
+            data.kyotaku, data.dora_marker, data.scores.map(|x| x.to_string()).join(" "),
+        ),
+    ];
+    for (seat, hand) in data.tehais.iter().enumerate() {
+        let mut hand = hand.to_vec();
+        hand.sort_by_key(|&x| u8::from(x));
+        lines.push(format!("S{} starts with {}", seat, tiles(&hand)));
+    }
+
+    // The player whose draw or call started the last line.
+    let mut turn: Option<u8> = None;
+    for event in &round.game {
+        let actor = event.actor();
+        match event {
+            MajEvent::Tsumo { actor, pai, .. } => lines.push(format!("S{} draws {}", actor, pai)),
+            MajEvent::Dahai { actor, pai, tsumogiri, .. } => {
+                append(&mut lines, &mut turn, *actor, &format!("discards {}{}", pai, if *tsumogiri { " (tsumogiri)" } else { "" }));
+            }
+            MajEvent::Reach { actor, step: ReachStep::Riichi, .. } => append(&mut lines, &mut turn, *actor, "declares riichi"),
+            MajEvent::Reach { actor, step: ReachStep::RiichiAccepted, .. } => append(&mut lines, &mut turn, *actor, "riichi accepted"),
+            MajEvent::Naki { actor, consumed, pai, target, kind, .. } => lines.push(match (kind, pai, target) {
+                (NakiKind::Chii, Some(pai), Some(target)) => format!("S{} chiis {} from S{} with {}", actor, pai, target, tiles(consumed)),
+                (NakiKind::Pon, Some(pai), Some(target)) => format!("S{} pons {} from S{}", actor, pai, target),
+                (NakiKind::Daiminkan, Some(pai), Some(target)) => format!("S{} kans {} from S{}", actor, pai, target),
+                (NakiKind::Kakan, Some(pai), _) => format!("S{} adds {} to a pon", actor, pai),
+                (NakiKind::Ankan, ..) => format!("S{} declares a closed kan of {}", actor, tiles(consumed)),
+                (NakiKind::Nukidora, ..) => format!("S{} declares kita", actor),
+                (kind, ..) => format!("S{} {}", actor, kind.name()),
+            }),
+            MajEvent::Dora { dora_marker } if turn.is_some() => lines.last_mut().unwrap().push_str(&format!("; new dora marker {}", dora_marker)),
+            MajEvent::Dora { dora_marker } => lines.push(format!("New dora marker {}", dora_marker)),
+            MajEvent::Agari { actor, fromwho, han, hu, score, yaku, deltas: changes, .. } => {
+                let win = if actor == fromwho { format!("S{} tsumo {}", actor, score) } else { format!("S{} ron {} from S{}", actor, score, fromwho) };
+                lines.push(format!("{} ({} han {} fu): {}", win, han, hu, yaku_list(yaku)));
+                lines.push(format!("  {}", deltas(changes)));
+            }
+            MajEvent::Ryuukyoku { reason, tenpai, deltas: changes, nagashi, .. } => {
+                lines.push(match reason {
+                    RyuukyokuReason::Howanpai if tenpai.contains(&true) => format!("Exhaustive draw, tenpai {}", seats(tenpai)),
+                    RyuukyokuReason::Howanpai => "Exhaustive draw, nobody tenpai".to_string(),
+                    RyuukyokuReason::Nm => format!("Exhaustive draw, nagashi mangan {}", seats(nagashi)),
+                    reason => format!("Abortive draw ({})", reason),
+                });
+                lines.push(format!("  {}", deltas(changes)));
+            }
+            MajEvent::Disconnect { actor } => lines.push(format!("S{} disconnected", actor)),
+            MajEvent::Reconnect { actor } => lines.push(format!("S{} reconnected", actor)),
+            MajEvent::Init { .. } | MajEvent::Unknown => {}
+        }
+        turn = match event {
+            MajEvent::Tsumo { .. } | MajEvent::Naki { .. } => actor,
+            MajEvent::Dahai { .. } | MajEvent::Reach { .. } | MajEvent::Dora { .. } => turn,
+            _ => None,
+        };
+    }
+    lines
+}
+
+/// A plain text account of the game, one paragraph per round, for reading or diffing by hand.
+/// Seats are written as `S0` to `S3` and tiles in the notation of the JSON output.
+pub fn transcript(game: &Game) -> String {
+    let mut text = String::new();
+    if let Some(meta) = &game.meta {
+        text.push_str(&format!("Log {}\n", meta.log_id));
+    }
+    for seat in 0..4 {
+        text.push_str(&format!("S{} {} ({}, R{:.0})\n", seat, game.id[seat], game.dan[seat].english_name(), game.rate[seat]));
+    }
+    for round in &game.games {
+        text.push('\n');
+        for line in round_lines(round) {
+            text.push_str(&line);
+            text.push('\n');
+        }
+    }
+    text.push_str(&format!("\nFinal scores {}\n", game.owari.map(|x| (x * 100).to_string()).join(" ")));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::round_lines;
+    use crate::enums::{MajEvent, NakiKind};
+    use crate::testing::RoundBuilder;
+
+    const TEHAIS: [&str; 4] = ["123456789m1234p", "123456789p1177z", "123456789s78p12m", "2223334445556z"];
+
+    #[test]
+    fn call_and_its_discard_share_a_line() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let called = b.tsumo(0, "7z");
+        b.riichi(0, called);
+        b.naki(1, NakiKind::Pon, &b.dealt(1, "77z"), Some(called), Some(0));
+        b.dahai(1, b.dealt(1, "9p")[0]);
+        let chii = b.dealt(2, "78p");
+        b.naki(2, NakiKind::Chii, &chii, Some(b.dealt(1, "9p")[0]), Some(1));
+        b.dahai(2, b.dealt(2, "1m")[0]);
+        let lines = round_lines(&b.build());
+
+        assert_eq!(lines[0], "East 1, 0 honba — dealer seat 0");
+        assert_eq!(lines[2], "S0 starts with 1m2m3m4m5m6m7m8m9m1p2p3p4p");
+        assert_eq!(lines[6..], [
+            "S0 draws 7z, declares riichi, discards 7z (tsumogiri), riichi accepted",
+            "S1 pons 7z from S0, discards 9p",
+            "S2 chiis 9p from S1 with 7p8p, discards 1m",
+        ]);
+    }
+
+    #[test]
+    fn discard_after_a_disconnect_names_the_player() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let drawn = b.tsumo(0, "7z");
+        let mut round = b.build();
+        round.game.push(MajEvent::Disconnect { actor: 0 });
+        round.game.push(MajEvent::Dahai { junme: 1, actor: 0, pai: drawn, tsumogiri: true });
+        round.game.push(MajEvent::Reconnect { actor: 0 });
+        round.game.push(MajEvent::Dahai { junme: 1, actor: 1, pai: round.data.tehais[1][0], tsumogiri: false });
+        let lines = round_lines(&round);
+        assert_eq!(lines[6..], [
+            "S0 draws 7z",
+            "S0 disconnected",
+            "S0 discards 7z (tsumogiri)",
+            "S0 reconnected",
+            "S1 discards 1p",
+        ]);
+    }
+}
@@ -1,5 +1,6 @@
+use percent_encoding::percent_decode_str;
 use quick_xml::events::BytesStart;
-use crate::enums::Pai;
+use crate::enums::{Pai, ANONYMOUS};
 
 pub trait IntoNumVec<T:> {
     fn into_num_vec(self) -> Vec<T>;
@@ -59,4 +60,17 @@ impl<'a> GetAttribute for BytesStart<'a> {
             a.as_ref().unwrap().key.as_ref() == key.as_bytes()
         }).map(|a| a.unwrap().value).map(|v| String::from_utf8(v.to_vec()).unwrap())
     }
-}
\ No newline at end of file
+}
+
+/// Tenhou percent-encodes the UTF-8 bytes of names in the UN tag.
+pub fn decode_name(name: &str) -> String {
+    percent_decode_str(name).decode_utf8_lossy().into_owned()
+}
+
+/// The inverse of `decode_name`. Tenhou percent-encodes every byte of a name, except for `NoName`.
+pub fn encode_name(name: &str) -> String {
+    if name == ANONYMOUS {
+        return name.to_string();
+    }
+    name.bytes().map(|x| format!("%{:02X}", x)).collect()
+}
@@ -0,0 +1,391 @@
+use std::fmt::{Display, Formatter};
+use crate::board::Board;
+use crate::enums::{MajEvent, NakiKind, Pai, PaiColor, ReachStep};
+use crate::round::{Game, Round};
+
+/// A broken invariant, from a corrupt log or a parser bug.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Index into `Game::games`.
+    pub round: usize,
+    /// Index into `Round::game`, `None` for the start of the round or the end of the game.
+    pub event: Option<usize>,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.event {
+            Some(event) => write!(f, "round {} event {}: {}", self.round + 1, event, self.message),
+            None => write!(f, "round {}: {}", self.round + 1, self.message),
+        }
+    }
+}
+
+/// Tiles of the wall that came into play. The wall itself is not in the log, so the tiles that were dealt,
+/// drawn or turned over as indicators are checked to be distinct tiles of the 136, and the draws and
+/// indicators to fit in the live wall and the dead wall left after the deal.
+struct Wall {
+    seen: [bool; 136],
+    draws: usize,
+    dora: usize,
+    ura: usize,
+}
+
+impl Wall {
+    /// Tiles the players draw after the deal, rinshan tiles included as the dead wall is refilled from the live wall.
+    const DRAWS: usize = 136 - 4 * 13 - 14;
+    /// Dora and ura indicators, one and up to four for kans.
+    const INDICATORS: usize = 5;
+
+    fn new() -> Self {
+        Self { seen: [false; 136], draws: 0, dora: 0, ura: 0 }
+    }
+
+    fn see(&mut self, pai: Pai, what: &str) -> Result<(), String> {
+        let valid = match pai.color {
+            PaiColor::Manzu | PaiColor::Pinzu | PaiColor::Souzu => (1..=9).contains(&pai.num),
+            PaiColor::Jihai => (1..=7).contains(&pai.num),
+            _ => false,
+        };
+        if !valid || pai.idx > 3 {
+            return Err(format!("{} tile {:?} is not one of the 136", what, pai));
+        }
+        let id = u8::from(pai) as usize;
+        if std::mem::replace(&mut self.seen[id], true) {
+            Err(format!("{} tile {} (id {}) is already in play", what, pai, id))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn draw(&mut self, pai: Pai) -> Result<(), String> {
+        self.draws += 1;
+        if self.draws > Self::DRAWS {
+            return Err(format!("draw {} of the round, but the wall has {} tiles to draw", self.draws, Self::DRAWS));
+        }
+        self.see(pai, "Drawn")
+    }
+
+    fn indicator(&mut self, pai: Pai, ura: bool) -> Result<(), String> {
+        let (count, what) = if ura { (&mut self.ura, "Ura indicator") } else { (&mut self.dora, "Dora indicator") };
+        *count += 1;
+        if *count > Self::INDICATORS {
+            return Err(format!("{} {}, but the dead wall has {}", what, count, Self::INDICATORS));
+        }
+        self.see(pai, what)
+    }
+}
+
+fn sorted(mut tiles: Vec<Pai>) -> Vec<Pai> {
+    tiles.sort_by_key(|&x| u8::from(x));
+    tiles
+}
+
+fn join(tiles: &[Pai]) -> String {
+    tiles.iter().map(|x| x.to_string()).collect()
+}
+
+struct RoundCheck<'a> {
+    round: usize,
+    violations: &'a mut Vec<Violation>,
+}
+
+impl RoundCheck<'_> {
+    fn check(&mut self, event: Option<usize>, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.violations.push(Violation { round: self.round, event, message });
+        }
+    }
+
+    /// Replays the round and returns the board after the last event.
+    fn replay(&mut self, round: &Round) -> Board {
+        let data = &round.data;
+        let mut board = Board::new(data);
+        let mut wall = Wall::new();
+        for (seat, hand) in data.tehais.iter().enumerate() {
+            for &pai in hand {
+                self.check(None, wall.see(pai, &format!("Dealt to player {}, the", seat)));
+            }
+        }
+        self.check(None, wall.indicator(data.dora_marker, false));
+
+        // The last discard that can be called or won on, and a kakan tile that can be robbed.
+        let mut discard: Option<(u8, Pai)> = None;
+        let mut kakan: Option<(u8, Pai)> = None;
+        let mut ura_seen = false;
+        // Players whose replayed hand went wrong, to report it once rather than at every turn.
+        let mut diverged = [false; 4];
+        for (i, event) in round.game.iter().enumerate() {
+            let at = Some(i);
+            match event {
+                MajEvent::Tsumo { pai, .. } => {
+                    self.check(at, wall.draw(*pai));
+                    discard = None;
+                    kakan = None;
+                }
+                MajEvent::Dora { dora_marker } => self.check(at, wall.indicator(*dora_marker, false)),
+                MajEvent::Naki { actor, pai: Some(pai), target: Some(target), kind, .. } if *kind != NakiKind::Kakan => {
+                    if discard != Some((*target, *pai)) {
+                        self.check(at, Err(format!("player {} calls {} from player {}, which is not the last discard", actor, pai, target)));
+                    }
+                    if *kind == NakiKind::Chii && *target != (actor + 3) % 4 {
+                        self.check(at, Err(format!("player {} calls chii from player {}, who does not sit to the left", actor, target)));
+                    }
+                }
+                MajEvent::Agari { actor, fromwho, machi, hai, ura_marker, deltas, .. } => {
+                    let winning = if actor == fromwho {
+                        board.drawn[*actor as usize] == Some(*machi)
+                    } else {
+                        [discard, kakan].contains(&Some((*fromwho, *machi)))
+                    };
+                    if !winning {
+                        self.check(at, Err(format!("player {} wins on {}, which player {} did not just draw or discard", actor, machi, fromwho)));
+                    }
+                    let mut hand = board.hands[*actor as usize].clone();
+                    hand.push(*machi);
+                    let (hand, hai) = (sorted(hand), sorted(hai.clone()));
+                    if hand != hai {
+                        self.check(at, Err(format!("winning hand {} of player {} differs from the replayed hand {}", join(&hai), actor, join(&hand))));
+                    }
+                    let sum: i32 = deltas.iter().sum();
+                    if sum != board.kyotaku as i32 * 1000 {
+                        self.check(at, Err(format!("score changes add up to {} with {} riichi sticks on the table", sum, board.kyotaku)));
+                    }
+                    // Every winner of a multiple ron shows the same ura indicators.
+                    if let Some(ura_marker) = ura_marker.as_ref().filter(|_| !ura_seen) {
+                        ura_marker.iter().for_each(|&x| self.check(at, wall.indicator(x, true)));
+                        ura_seen = true;
+                    }
+                }
+                MajEvent::Ryuukyoku { deltas, .. } => {
+                    let sum: i32 = deltas.iter().sum();
+                    if sum != 0 {
+                        self.check(at, Err(format!("score changes of the draw add up to {}", sum)));
+                    }
+                }
+                MajEvent::Reach { actor, step: ReachStep::RiichiAccepted, scores: Some(scores), .. } => {
+                    let mut expected = board.scores;
+                    expected[*actor as usize] -= 1000;
+                    if expected != *scores {
+                        self.check(at, Err(format!("scores after the riichi deposit are {:?}, not {:?}", scores, expected)));
+                    }
+                }
+                _ => {}
+            }
+
+            let applied = board.apply(event);
+            if let (Err(_), Some(actor)) = (&applied, event.actor()) {
+                diverged[actor as usize] = true;
+            }
+            self.check(at, applied);
+
+            match event {
+                MajEvent::Dahai { actor, pai, .. } => discard = Some((*actor, *pai)),
+                MajEvent::Naki { actor, pai: Some(pai), kind: NakiKind::Kakan, .. } => kakan = Some((*actor, *pai)),
+                _ => {}
+            }
+            let expected = match event {
+                MajEvent::Tsumo { .. } | MajEvent::Naki { kind: NakiKind::Chii | NakiKind::Pon, .. } => Some(14),
+                MajEvent::Dahai { .. } => Some(13),
+                _ => None,
+            };
+            if let (Some(expected), Some(actor)) = (expected, event.actor().filter(|&x| !diverged[x as usize])) {
+                // A kita is set aside like a kan and replaced from the dead wall, so it is not counted.
+                let melds = board.melds[actor as usize].iter().filter(|x| x.kind != NakiKind::Nukidora).count();
+                let size = board.concealed(actor) + 3 * melds;
+                if size != expected {
+                    diverged[actor as usize] = true;
+                    self.check(at, Err(format!("player {} holds {} tiles counting melds as 3, not {}", actor, size, expected)));
+                }
+            }
+        }
+        board
+    }
+}
+
+/// Replays every round and checks that tile ids are valid and not used twice, draws and indicators fit in the wall, hands have 13 or 14 tiles without kita,
+/// discards and calls use tiles that were there, score changes only move points around,
+/// rounds start with the scores the previous round ended with, and `owari` matches the end.
+pub fn validate(game: &Game) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut previous: Option<Board> = None;
+    let total = game.games.first().map(|x| x.data.scores.iter().sum::<i32>() + x.data.kyotaku as i32 * 1000);
+    for (i, round) in game.games.iter().enumerate() {
+        let mut check = RoundCheck { round: i, violations: &mut violations };
+        let data = &round.data;
+        if let Some(previous) = &previous {
+            if previous.scores != data.scores || previous.kyotaku != data.kyotaku {
+                let message = format!(
+                    "starts with scores {:?} and {} riichi sticks, but the previous round ended with {:?} and {}",
+                    data.scores, data.kyotaku, previous.scores, previous.kyotaku,
+                );
+                check.check(None, Err(message));
+            }
+        }
+        let sum = data.scores.iter().sum::<i32>() + data.kyotaku as i32 * 1000;
+        if Some(sum) != total {
+            check.check(None, Err(format!("scores and riichi sticks add up to {}, not {} as in the first round", sum, total.unwrap())));
+        }
+        previous = Some(check.replay(round));
+    }
+
+    if let Some(last) = previous {
+        // Riichi sticks left at the end of the game go to the first place.
+        let mut expected = last.scores;
+        let top = (0..4).max_by_key(|&x| (expected[x], std::cmp::Reverse(x))).unwrap();
+        expected[top] += last.kyotaku as i32 * 1000;
+        let owari = game.owari.map(|x| x * 100);
+        if owari != expected {
+            violations.push(Violation {
+                round: game.games.len() - 1,
+                event: None,
+                message: format!("owari scores {:?} differ from the scores {:?} at the end of the game", owari, expected),
+            });
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+    use crate::enums::{MajEvent, NakiKind, Pai, PaiColor, RyuukyokuReason};
+    use crate::round::{Game, Round};
+    use crate::testing::{fixture, RoundBuilder};
+
+    const TEHAIS: [&str; 4] = ["123456789m1234z", "123456789p1177z", "123456789s1234m", "2223334445556z"];
+
+    /// A game of one round whose `owari` matches the end of the round.
+    fn game(round: Round) -> Game {
+        let board = round.board_at(round.game.len());
+        let mut owari = board.scores;
+        let top = (0..4).max_by_key(|&x| (owari[x], std::cmp::Reverse(x))).unwrap();
+        owari[top] += board.kyotaku as i32 * 1000;
+        Game { owari: owari.map(|x| x / 100), games: vec![round], ..Default::default() }
+    }
+
+    fn violations(round: Round) -> Vec<(Option<usize>, String)> {
+        validate(&game(round)).into_iter().map(|x| (x.event, x.message)).collect()
+    }
+
+    /// Seat 0 declares riichi and wins on the discard of seat 1.
+    fn riichi_and_ron() -> RoundBuilder {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let declared = b.tsumo(0, "7z");
+        b.riichi(0, declared);
+        let machi = b.tsumo(1, "6z");
+        b.dahai(1, machi);
+        b.agari(0, 1, machi, 1000, [2000, -1000, 0, 0]);
+        b
+    }
+
+    #[test]
+    fn valid_rounds_have_no_violations() {
+        assert_eq!(violations(riichi_and_ron().build()), []);
+        assert!(validate(&fixture()).is_empty());
+    }
+
+    #[test]
+    fn kita_is_not_counted_as_a_meld() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let drawn = b.tsumo(0, "9s");
+        b.naki(0, NakiKind::Nukidora, &b.dealt(0, "4z"), None, None);
+        b.tsumo(0, "6z");
+        b.dahai(0, drawn);
+        b.ryuukyoku(RyuukyokuReason::Howanpai, [false; 4], [0; 4]);
+        assert_eq!(violations(b.build()), []);
+    }
+
+    #[test]
+    fn tile_in_play_twice() {
+        let mut round = riichi_and_ron().build();
+        let dealt = round.data.tehais[1][0];
+        round.game.insert(0, MajEvent::Tsumo { junme: 1, actor: 2, pai: dealt });
+        round.game.insert(1, MajEvent::Dahai { junme: 1, actor: 2, pai: dealt, tsumogiri: true });
+        let violations = violations(round);
+        assert_eq!(violations[0], (Some(0), "Drawn tile 1p (id 36) is already in play".to_string()));
+    }
+
+    #[test]
+    fn tile_outside_the_136() {
+        let mut round = riichi_and_ron().build();
+        round.game.insert(0, MajEvent::Dora { dora_marker: Pai { num: 5, color: PaiColor::Manzu, idx: 4 } });
+        let violations = violations(round);
+        assert_eq!(violations[0], (Some(0), "Dora indicator tile Pai { num: 5, color: Manzu, idx: 4 } is not one of the 136".to_string()));
+    }
+
+    #[test]
+    fn draws_and_indicators_must_fit_in_the_wall() {
+        let mut round = RoundBuilder::new(0, TEHAIS).build();
+        let mut used = [false; 136];
+        for &pai in round.data.tehais.iter().flatten().chain([&round.data.dora_marker]) {
+            used[u8::from(pai) as usize] = true;
+        }
+        let mut free = (0..136u8).filter(|&id| !used[id as usize]).map(Pai::from);
+        for i in 0..71 {
+            let pai = free.next().unwrap();
+            let (junme, actor) = (i / 4 + 1, i % 4);
+            round.game.push(MajEvent::Tsumo { junme, actor, pai });
+            round.game.push(MajEvent::Dahai { junme, actor, pai, tsumogiri: true });
+        }
+        for dora_marker in free.take(5) {
+            round.game.push(MajEvent::Dora { dora_marker });
+        }
+        let violations = violations(round);
+        assert_eq!(violations, [
+            (Some(140), "draw 71 of the round, but the wall has 70 tiles to draw".to_string()),
+            (Some(146), "Dora indicator 6, but the dead wall has 5".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn discard_from_another_hand() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        b.tsumo(0, "7z");
+        b.dahai(0, b.dealt(1, "1p")[0]);
+        let violations = violations(b.build());
+        assert!(violations.contains(&(Some(1), "1p is not in the hand of player 0".to_string())), "{:?}", violations);
+    }
+
+    #[test]
+    fn call_of_an_older_discard_and_chii_across_the_table() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        let old = b.tsumo(0, "9s");
+        b.dahai(0, old);
+        let last = b.tsumo(1, "6z");
+        b.dahai(1, last);
+        b.naki(2, NakiKind::Chii, &b.dealt(2, "78s"), Some(old), Some(0));
+        let messages = violations(b.build()).into_iter().map(|x| x.1).collect::<Vec<_>>();
+        assert!(messages.contains(&"player 2 calls 9s from player 0, which is not the last discard".to_string()));
+        assert!(messages.contains(&"player 2 calls chii from player 0, who does not sit to the left".to_string()));
+    }
+
+    #[test]
+    fn hand_with_a_tile_too_many_is_reported_once() {
+        let mut b = RoundBuilder::new(0, TEHAIS);
+        b.tsumo(0, "9s");
+        b.tsumo(0, "8s");
+        b.tsumo(0, "7s");
+        let violations = violations(b.build());
+        assert_eq!(violations, [(Some(1), "player 0 holds 15 tiles counting melds as 3, not 14".to_string())]);
+    }
+
+    #[test]
+    fn score_changes_must_only_move_points() {
+        let mut round = riichi_and_ron().build();
+        let Some(MajEvent::Agari { deltas, .. }) = round.game.last_mut() else { unreachable!() };
+        deltas[0] += 300;
+        let violations = violations(round);
+        assert_eq!(violations[0].1, "score changes add up to 1300 with 1 riichi sticks on the table");
+    }
+
+    #[test]
+    fn owari_must_match_the_end_of_the_game() {
+        let mut game = game(riichi_and_ron().build());
+        game.owari[1] -= 10;
+        let violations = validate(&game);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].message.starts_with("owari scores"));
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::Path;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use tenhou_parser_rs::board::Board;
+use tenhou_parser_rs::enums::MajEvent;
+use tenhou_parser_rs::round::Game;
+
+/// Reads mjlog XML, or JSON written by this tool if the extension is `.json`.
+pub fn load_game(path: &str) -> Game {
+    if Path::new(path).extension().is_some_and(|x| x == "json") {
+        Game::parse_json_file(path)
+    } else {
+        Game::parse_xml_file(path)
+    }
+}
+
+fn round_name(game: &Game, round: usize) -> String {
+    let data = &game.games[round].data;
+    format!("{}{}-{}", data.bakaze, data.kyoku, data.honba)
+}
+
+fn describe(event: &MajEvent) -> String {
+    match (event.actor(), event.pai()) {
+        (Some(actor), Some(pai)) => format!("P{} {} {}", actor, event.type_name(), pai),
+        (Some(actor), None) => format!("P{} {}", actor, event.type_name()),
+        (None, Some(pai)) => format!("{} {}", event.type_name(), pai),
+        (None, None) => event.type_name().to_string(),
+    }
+}
+
+/// Replay position: the board shows the round before the event at `event`.
+struct Viewer<'a> {
+    game: &'a Game,
+    round: usize,
+    event: usize,
+    /// Digits typed for a jump to a round.
+    jump: String,
+}
+
+impl Viewer<'_> {
+    fn events(&self) -> usize {
+        self.game.games[self.round].game.len()
+    }
+
+    fn set_round(&mut self, round: usize) {
+        self.round = round.min(self.game.games.len() - 1);
+        self.event = 0;
+    }
+
+    /// Returns `false` to quit.
+    fn key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => self.event = (self.event + 1).min(self.events()),
+            KeyCode::Left | KeyCode::Char('h') => self.event = self.event.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.set_round(self.round + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.set_round(self.round.saturating_sub(1)),
+            KeyCode::Home => self.event = 0,
+            KeyCode::End => self.event = self.events(),
+            KeyCode::Char(c) if c.is_ascii_digit() => self.jump.push(c),
+            KeyCode::Backspace => {
+                self.jump.pop();
+            }
+            KeyCode::Enter => {
+                if let Ok(round) = self.jump.parse::<usize>() {
+                    self.set_round(round.saturating_sub(1));
+                }
+                self.jump.clear();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn player(&self, board: &Board, seat: usize) -> Vec<Line<'static>> {
+        let game = self.game;
+        let data = &game.games[self.round].data;
+        let mut title = format!("P{} {} {} {}", seat, game.id[seat], game.dan[seat], board.scores[seat]);
+        if data.oya as usize == seat {
+            title.push_str(" dealer");
+        }
+        if board.riichi[seat] {
+            title.push_str(" riichi");
+        }
+
+        let mut hand: Vec<Span> = board.hands[seat].iter().map(|x| Span::raw(format!("{} ", x))).collect();
+        if let Some(drawn) = board.drawn[seat] {
+            hand.push(Span::styled(format!(" {}", drawn), Style::new().bold()));
+        }
+        for meld in &board.melds[seat] {
+            let tiles = meld.tiles.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("");
+            hand.push(Span::styled(format!("  [{} {}]", meld.kind.name(), tiles), Style::new().cyan()));
+        }
+
+        let river = board.rivers()[seat].tiles.iter().map(|tile| {
+            let mut style = Style::new();
+            if tile.tsumogiri {
+                style = style.add_modifier(Modifier::DIM);
+            }
+            if tile.called.is_some() {
+                style = style.add_modifier(Modifier::CROSSED_OUT);
+            }
+            let text = if tile.sideways { format!("<{}> ", tile.pai) } else { format!("{} ", tile.pai) };
+            Span::styled(text, style)
+        }).collect::<Vec<Span>>();
+
+        vec![Line::from(title).bold(), Line::from(hand), Line::from(river)]
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let round = &self.game.games[self.round];
+        let board = round.board_at(self.event);
+        let last = self.event.checked_sub(1).map(|i| describe(&round.game[i])).unwrap_or_else(|| "start".to_string());
+        let dora = board.dora_markers.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ");
+        let header = format!(
+            "Round {}/{} {}  kyotaku {}  dora markers {}  event {}/{}: {}",
+            self.round + 1, self.game.games.len(), round_name(self.game, self.round), board.kyotaku, dora, self.event, self.events(), last,
+        );
+        let help = format!("←/→ event  ↑/↓ round  Home/End  digits+Enter jump to round {}  q quit", self.jump);
+
+        let [header_area, players_area, help_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        frame.render_widget(Paragraph::new(header), header_area);
+        let areas = Layout::vertical([Constraint::Ratio(1, 4); 4]).split(players_area);
+        for seat in 0..4 {
+            frame.render_widget(Paragraph::new(self.player(&board, seat)).block(Block::bordered()), areas[seat]);
+        }
+        frame.render_widget(Paragraph::new(help).dim(), help_area);
+    }
+
+    fn run(&mut self, mut terminal: DefaultTerminal) {
+        loop {
+            terminal.draw(|frame| self.draw(frame)).unwrap();
+            if let Event::Key(key) = event::read().unwrap() {
+                if key.kind == KeyEventKind::Press && !self.key(key.code) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Opens the replay at the start of `round`, counted from 1.
+pub fn view(game: &Game, round: usize) {
+    if game.games.is_empty() {
+        log::error!("No rounds to show");
+        return;
+    }
+    let mut viewer = Viewer { game, round: 0, event: 0, jump: String::new() };
+    viewer.set_round(round.saturating_sub(1));
+    let terminal = ratatui::init();
+    viewer.run(terminal);
+    ratatui::restore();
+}
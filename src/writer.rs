@@ -0,0 +1,321 @@
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use crate::columnar::ParquetDataset;
+use crate::csv_tables::CsvTables;
+use crate::encoding::Encoding;
+use crate::round::Game;
+use crate::sqlite::SqliteDatabase;
+use crate::transcript::transcript;
+
+/// A sink for parsed games. Writers are opened on an output path by a `WriterRegistry` entry,
+/// receive every game with its log id, and are finished once after the last game.
+pub trait GameWriter {
+    /// Whether the game can be skipped without parsing, e.g. because it was written in an earlier run.
+    fn contains(&self, _log_id: &str) -> bool {
+        false
+    }
+
+    fn write(&mut self, log_id: &str, game: &Game);
+
+    fn finish(self: Box<Self>) {}
+}
+
+/// One file per game named after the log id. If the output is not an existing directory,
+/// it is taken as the file name of a single game.
+pub struct FileWriter {
+    output: PathBuf,
+    encoding: Encoding,
+    written: bool,
+}
+
+impl FileWriter {
+    pub fn new<P: AsRef<Path>>(output: P, encoding: Encoding) -> Self {
+        Self { output: output.as_ref().to_path_buf(), encoding, written: false }
+    }
+}
+
+/// The file for `log_id` under `output`, which is a directory or the file of a single game.
+fn file_path(output: &Path, written: &mut bool, log_id: &str, extension: &str) -> PathBuf {
+    let path = if output.is_dir() {
+        output.join(log_id).with_extension(extension)
+    } else {
+        assert!(!*written, "Output directory {:?} does not exist", output);
+        output.with_extension(extension)
+    };
+    *written = true;
+    path
+}
+
+impl GameWriter for FileWriter {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        let path = file_path(&self.output, &mut self.written, log_id, self.encoding.extension());
+        game.write_to_file(path, self.encoding);
+    }
+}
+
+/// One plain text transcript per game, laid out like `FileWriter`.
+pub struct TextWriter {
+    output: PathBuf,
+    written: bool,
+}
+
+impl TextWriter {
+    pub fn new<P: AsRef<Path>>(output: P) -> Self {
+        Self { output: output.as_ref().to_path_buf(), written: false }
+    }
+}
+
+impl GameWriter for TextWriter {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        let path = file_path(&self.output, &mut self.written, log_id, "txt");
+        std::fs::write(path, transcript(game)).unwrap();
+    }
+}
+
+/// One file per game in a subdirectory per day, taken from the `yyyymmdd` prefix of Tenhou log ids.
+/// Log ids without a date prefix go to the top directory.
+pub struct DailyWriter {
+    dir: PathBuf,
+    encoding: Encoding,
+}
+
+impl DailyWriter {
+    pub fn new<P: AsRef<Path>>(dir: P, encoding: Encoding) -> Self {
+        Self { dir: dir.as_ref().to_path_buf(), encoding }
+    }
+}
+
+impl GameWriter for DailyWriter {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        let dir = match log_id.get(..8) {
+            Some(day) if day.bytes().all(|x| x.is_ascii_digit()) => self.dir.join(day),
+            _ => self.dir.clone(),
+        };
+        create_dir_all(&dir).unwrap();
+        game.write_to_file(dir.join(log_id).with_extension(self.encoding.extension()), self.encoding);
+    }
+}
+
+#[derive(Serialize)]
+struct JsonlRecord<'a> {
+    log_id: &'a str,
+    #[serde(flatten)]
+    game: &'a Game,
+}
+
+/// One mjlog XML file per game in a directory, re-emitted from the parsed game.
+pub struct XmlWriter {
+    dir: PathBuf,
+}
+
+impl XmlWriter {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        create_dir_all(dir.as_ref()).unwrap();
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+}
+
+impl GameWriter for XmlWriter {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        game.write_to_xml(self.dir.join(log_id).with_extension("xml"));
+    }
+}
+
+/// The file of a `JsonlWriter`, gzip-compressed or not.
+pub enum JsonlOutput {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl JsonlOutput {
+    /// Gzip-compresses the file if the path ends with `.gz`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let file = BufWriter::new(File::create(path).unwrap());
+        if path.extension().is_some_and(|x| x == "gz") {
+            JsonlOutput::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            JsonlOutput::Plain(file)
+        }
+    }
+
+    /// Writes the gzip trailer and flushes the file.
+    pub fn finish(self) -> std::io::Result<()> {
+        match self {
+            JsonlOutput::Plain(mut file) => file.flush(),
+            JsonlOutput::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for JsonlOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            JsonlOutput::Plain(file) => file.write(buf),
+            JsonlOutput::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            JsonlOutput::Plain(file) => file.flush(),
+            JsonlOutput::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// All games in one file, one minified JSON object per line with the log id in a `log_id` field.
+pub struct JsonlWriter {
+    output: JsonlOutput,
+}
+
+impl JsonlWriter {
+    pub fn new(output: JsonlOutput) -> Self {
+        Self { output }
+    }
+
+    /// Gzip-compresses the file if the path ends with `.gz`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Self {
+        Self::new(JsonlOutput::create(path))
+    }
+}
+
+impl GameWriter for JsonlWriter {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        serde_json::to_writer(&mut self.output, &JsonlRecord { log_id, game }).unwrap();
+        self.output.write_all(b"\n").unwrap();
+    }
+
+    fn finish(self: Box<Self>) {
+        self.output.finish().unwrap();
+    }
+}
+
+impl GameWriter for ParquetDataset {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        self.append(log_id, game);
+    }
+
+    fn finish(self: Box<Self>) {
+        ParquetDataset::finish(*self);
+    }
+}
+
+impl GameWriter for SqliteDatabase {
+    fn contains(&self, log_id: &str) -> bool {
+        SqliteDatabase::contains(self, log_id)
+    }
+
+    fn write(&mut self, log_id: &str, game: &Game) {
+        self.insert(log_id, game);
+    }
+
+    fn finish(self: Box<Self>) {
+        SqliteDatabase::finish(*self);
+    }
+}
+
+impl GameWriter for CsvTables {
+    fn write(&mut self, log_id: &str, game: &Game) {
+        CsvTables::write(self, log_id, game);
+    }
+
+    fn finish(self: Box<Self>) {
+        CsvTables::finish(*self);
+    }
+}
+
+pub type OpenWriter = fn(&Path) -> Box<dyn GameWriter>;
+
+pub struct WriterEntry {
+    pub name: &'static str,
+    pub help: &'static str,
+    /// Writes one output per input, so the output path defaults to the input.
+    pub per_input: bool,
+    pub open: OpenWriter,
+}
+
+/// Writers selectable by name. `WriterRegistry::default()` has the built-in formats,
+/// and other crates can `register` their own before handing the registry to their CLI.
+pub struct WriterRegistry {
+    entries: Vec<WriterEntry>,
+}
+
+impl WriterRegistry {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    pub fn register(&mut self, entry: WriterEntry) {
+        self.entries.retain(|x| x.name != entry.name);
+        self.entries.push(entry);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WriterEntry> {
+        self.entries.iter().find(|x| x.name == name)
+    }
+
+    pub fn entries(&self) -> &[WriterEntry] {
+        &self.entries
+    }
+}
+
+impl Default for WriterRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        for entry in [
+            WriterEntry { name: "json", help: "One pretty-printed JSON file per input", per_input: true, open: |x| Box::new(FileWriter::new(x, Encoding::PrettyJson)) },
+            WriterEntry { name: "json-min", help: "One minified JSON file per input", per_input: true, open: |x| Box::new(FileWriter::new(x, Encoding::Json)) },
+            WriterEntry { name: "msgpack", help: "One MessagePack file per input", per_input: true, open: |x| Box::new(FileWriter::new(x, Encoding::MessagePack)) },
+            WriterEntry { name: "cbor", help: "One CBOR file per input", per_input: true, open: |x| Box::new(FileWriter::new(x, Encoding::Cbor)) },
+            WriterEntry { name: "text", help: "One plain text transcript per input", per_input: true, open: |x| Box::new(TextWriter::new(x)) },
+            WriterEntry { name: "json-daily", help: "One JSON file per input in a subdirectory per day", per_input: false, open: |x| Box::new(DailyWriter::new(x, Encoding::PrettyJson)) },
+            WriterEntry { name: "mjlog", help: "One mjlog XML file per input in a separate directory", per_input: false, open: |x| Box::new(XmlWriter::new(x)) },
+            WriterEntry { name: "jsonl", help: "One JSON Lines file for all inputs, gzip-compressed if it ends with .gz", per_input: false, open: |x| Box::new(JsonlWriter::create(x)) },
+            WriterEntry { name: "parquet", help: "One Parquet dataset for all inputs, a subdirectory per table", per_input: false, open: |x| Box::new(ParquetDataset::open(x)) },
+            WriterEntry { name: "sqlite", help: "One SQLite database for all inputs, skipping already imported logs", per_input: false, open: |x| Box::new(SqliteDatabase::open(x)) },
+            WriterEntry { name: "csv", help: "One CSV file per table for all inputs", per_input: false, open: |x| Box::new(CsvTables::create(x)) },
+        ] {
+            registry.register(entry);
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Read};
+    use flate2::read::GzDecoder;
+    use super::{GameWriter, JsonlWriter};
+    use crate::testing::{fixture, temp_dir};
+
+    fn write_jsonl(path: &std::path::Path) {
+        let mut writer: Box<dyn GameWriter> = Box::new(JsonlWriter::create(path));
+        writer.write("a", &fixture());
+        writer.write("b", &fixture());
+        writer.finish();
+    }
+
+    #[test]
+    fn gzip_stream_is_complete() {
+        let path = temp_dir("jsonl-gz").join("games.jsonl.gz");
+        write_jsonl(&path);
+        // A stream without its trailer fails with an unexpected end of file.
+        let mut text = String::new();
+        GzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut text).unwrap();
+        let ids = text.lines().map(|x| serde_json::from_str::<serde_json::Value>(x).unwrap()["log_id"].clone()).collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "b"]);
+    }
+
+    #[test]
+    fn plain_file_has_one_game_per_line() {
+        let path = temp_dir("jsonl").join("games.jsonl");
+        write_jsonl(&path);
+        assert_eq!(BufReader::new(File::open(&path).unwrap()).lines().count(), 2);
+    }
+}
@@ -1,4 +1,3 @@
-#[allow(dead_code)]
 const YAKUS: [(i32, &str, &str); 55] = [
     (0, "menze_tsumo", "門前清自摸和"),
     (1, "riichi", "立直"),
@@ -54,4 +53,24 @@ const YAKUS: [(i32, &str, &str); 55] = [
     (51, "suukantsu", "四槓子"),
     (52, "dora", "ドラ"),
     (53, "uradora", "裏ドラ"),
-    (54, "akadora", "赤ドラ")];
\ No newline at end of file
+    (54, "akadora", "赤ドラ")];
+
+pub fn yaku_name(yaku: u8) -> &'static str {
+    YAKUS[yaku as usize].1
+}
+
+/// Han of a non-dora yaku, used to rebuild the `yaku` attribute from the flattened yaku list.
+pub(crate) fn yaku_han(yaku: u8, menzen: bool) -> u8 {
+    match yaku {
+        21 | 22 | 26..=31 => 2,
+        23..=25 if menzen => 2,
+        23..=25 => 1,
+        32 => 3,
+        33 | 34 if menzen => 3,
+        33 | 34 => 2,
+        35 if menzen => 6,
+        35 => 5,
+        36..=51 => 13,
+        _ => 1,
+    }
+}